@@ -0,0 +1,15 @@
+//! Instances shared by the benchmarks.
+
+use parallelized_acotsp::InstanceData;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Cities spread uniformly over a 1000 x 1000 square, always the same ones for
+/// a given size.
+pub fn random_instance(size: usize) -> InstanceData {
+    let mut rng = StdRng::seed_from_u64(0);
+    let coordinates: Vec<(f64, f64)> = (0..size)
+        .map(|_| (rng.gen_range(0.0, 1000.0), rng.gen_range(0.0, 1000.0)))
+        .collect();
+    InstanceData::from_coordinates(&coordinates)
+}
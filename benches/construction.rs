@@ -0,0 +1,104 @@
+//! Measures solution construction, comparing a single MMAS ant with the way
+//! ants built their tours when those were an `IndexSet`.
+
+mod common;
+
+use common::random_instance;
+use criterion::{criterion_group, criterion_main, Criterion};
+use indexmap::IndexSet;
+use parallelized_acotsp::aco::{
+    ant_rng, compute_combined_info, mmas_ant, pheromone_values, Ant, ColonyRegistry, EdgeValues,
+};
+use parallelized_acotsp::{AcoParameters, Algorithm, InstanceData};
+use rand::rngs::StdRng;
+use rand::Rng;
+
+const NUM_CITIES: usize = 200;
+
+/// Builds a tour the way ants did when their tour was an `IndexSet`: checking
+/// whether each city of the row was visited by hashing it, and collecting the
+/// unvisited ones with their weights before choosing.
+fn reference_mmas_ant(data: &InstanceData, combined_info: &EdgeValues, rng: &mut StdRng) -> u32 {
+    let starting_city = rng.gen_range(0, data.size);
+    let mut tour = IndexSet::with_capacity(data.size);
+    tour.insert(starting_city);
+    let mut curr = starting_city;
+    let mut length = 0;
+    for _ in 1..data.size {
+        let (unvisited, weights): (Vec<usize>, Vec<f64>) = (0..data.size)
+            .filter(|city| !tour.contains(city))
+            .map(|city| (city, combined_info.get(curr, city)))
+            .unzip();
+        let mut random_v = rng.gen::<f64>() * weights.iter().sum::<f64>();
+        let mut next = *unvisited.last().expect("every city was visited");
+        for (&city, weight) in unvisited.iter().zip(weights) {
+            random_v -= weight;
+            if random_v < 0.0 {
+                next = city;
+                break;
+            }
+        }
+        tour.insert(next);
+        length += data.distance(curr, next);
+        curr = next;
+    }
+    length + data.distance(curr, starting_city)
+}
+
+fn construct_solutions(c: &mut Criterion, algorithm: Algorithm, name: &str) {
+    let data = random_instance(NUM_CITIES);
+    let parameters = AcoParameters::builder(algorithm)
+        .num_ants(10)
+        .seed(1)
+        .build();
+    let registry = ColonyRegistry::with_builtin_colonies();
+    let mut colony = registry
+        .create(parameters.algorithm.name(), &data, &parameters)
+        .unwrap();
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            colony.new_iteration();
+            colony.construct_solutions()
+        })
+    });
+}
+
+fn single_mmas_ant(c: &mut Criterion) {
+    let data = random_instance(NUM_CITIES);
+    let parameters = AcoParameters::builder(Algorithm::Mmas).build();
+    let pheromones = pheromone_values(&data, &parameters, 1.0);
+    let (_, combined_info) = compute_combined_info(&data, &pheromones, &parameters);
+    let mut ant = Ant::new(data.size);
+    let mut iteration = 0;
+    c.bench_function("mmas_ant reference", |b| {
+        b.iter(|| {
+            iteration += 1;
+            let mut rng = ant_rng(1, iteration, 0);
+            reference_mmas_ant(&data, &combined_info, &mut rng)
+        })
+    });
+    c.bench_function("mmas_ant", |b| {
+        b.iter(|| {
+            iteration += 1;
+            let mut rng = ant_rng(1, iteration, 0);
+            mmas_ant(&mut ant, &data, &combined_info, &mut rng);
+            ant.length
+        })
+    });
+}
+
+fn mmas_construction(c: &mut Criterion) {
+    construct_solutions(c, Algorithm::Mmas, "mmas construct_solutions");
+}
+
+fn acs_construction(c: &mut Criterion) {
+    construct_solutions(c, Algorithm::Acs, "acs construct_solutions");
+}
+
+criterion_group!(
+    benches,
+    single_mmas_ant,
+    mmas_construction,
+    acs_construction
+);
+criterion_main!(benches);
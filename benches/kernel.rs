@@ -0,0 +1,66 @@
+//! Compares the construction kernels with the way the next city used to be
+//! chosen: filtering a row down to the unvisited cities, collecting them with
+//! their weights and walking the result.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use parallelized_acotsp::aco::kernel;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const ROW_SIZE: usize = 1000;
+
+fn reference_roulette(row: &[f64], visited: &[bool], r: f64) -> usize {
+    let (unvisited, weights): (Vec<usize>, Vec<f64>) =
+        row.iter().enumerate().filter(|(i, _)| !visited[*i]).unzip();
+    let mut random_v = r * weights.iter().sum::<f64>();
+    for (city, weight) in unvisited.iter().zip(weights) {
+        random_v -= weight;
+        if random_v < 0.0 {
+            return *city;
+        }
+    }
+    unreachable!()
+}
+
+fn reference_max(row: &[f64], visited: &[bool]) -> usize {
+    let (i, _) = row
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !visited[*i])
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).expect("failed comparison"))
+        .expect("failed max_by");
+    i
+}
+
+/// A row of combined information with about half of the cities visited.
+fn row_and_masks() -> (Vec<f64>, Vec<bool>, Vec<f64>) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let row: Vec<f64> = (0..ROW_SIZE).map(|_| rng.gen()).collect();
+    let visited: Vec<bool> = (0..ROW_SIZE).map(|_| rng.gen()).collect();
+    let unvisited = visited.iter().map(|&v| if v { 0.0 } else { 1.0 }).collect();
+    (row, visited, unvisited)
+}
+
+fn roulette(c: &mut Criterion) {
+    let (row, visited, unvisited) = row_and_masks();
+    let mut rng = StdRng::seed_from_u64(1);
+    c.bench_function("roulette reference", |b| {
+        b.iter(|| reference_roulette(&row, &visited, rng.gen()))
+    });
+    c.bench_function("roulette kernel", |b| {
+        b.iter(|| kernel::roulette(&row, &unvisited, rng.gen()))
+    });
+}
+
+fn max(c: &mut Criterion) {
+    let (row, visited, unvisited) = row_and_masks();
+    c.bench_function("max reference", |b| {
+        b.iter(|| reference_max(&row, &visited))
+    });
+    c.bench_function("max kernel", |b| {
+        b.iter(|| kernel::masked_max(&row, &unvisited))
+    });
+}
+
+criterion_group!(benches, roulette, max);
+criterion_main!(benches);
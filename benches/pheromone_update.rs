@@ -0,0 +1,39 @@
+mod common;
+
+use common::random_instance;
+use criterion::{criterion_group, criterion_main, Criterion};
+use parallelized_acotsp::aco::ColonyRegistry;
+use parallelized_acotsp::{AcoParameters, Algorithm};
+
+const NUM_CITIES: usize = 2000;
+
+/// Updates the pheromones with the same ants every time, so only the update
+/// is measured.
+fn update_pheromones(c: &mut Criterion, algorithm: Algorithm, name: &str) {
+    let data = random_instance(NUM_CITIES);
+    let parameters = AcoParameters::builder(algorithm)
+        .num_ants(10)
+        .seed(1)
+        .build();
+    let registry = ColonyRegistry::with_builtin_colonies();
+    let mut colony = registry
+        .create(parameters.algorithm.name(), &data, &parameters)
+        .unwrap();
+    colony.new_iteration();
+    let population = colony.construct_solutions();
+    let best = population.ant(population.best_index());
+    c.bench_function(name, |b| {
+        b.iter(|| colony.update_pheromones(&population, &best))
+    });
+}
+
+fn mmas_update(c: &mut Criterion) {
+    update_pheromones(c, Algorithm::Mmas, "mmas update_pheromones");
+}
+
+fn mmas_par_update(c: &mut Criterion) {
+    update_pheromones(c, Algorithm::MmasPar, "mmaspar update_pheromones");
+}
+
+criterion_group!(benches, mmas_update, mmas_par_update);
+criterion_main!(benches);
@@ -1,20 +1,113 @@
+use crate::instance_data::{InstanceData, MAX_MATRIX_SIZE};
+use serde::{Deserialize, Deserializer};
 use serde_derive::Deserialize;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Deserialize, Debug)]
+/// Which colony to run. Besides the built-in ones, any colony registered in a
+/// `ColonyRegistry` can be selected by its name.
+#[derive(Debug, Default, Clone, PartialEq)]
 pub enum Algorithm {
     Mmas,
     MmasPar,
+    #[default]
     Acs,
     AcsPar,
+    /// A colony registered under this name
+    Custom(String),
 }
 
-impl Default for Algorithm {
-    fn default() -> Algorithm {
-        Algorithm::Acs
+impl Algorithm {
+    pub fn name(&self) -> &str {
+        match self {
+            Algorithm::Mmas => "Mmas",
+            Algorithm::MmasPar => "MmasPar",
+            Algorithm::Acs => "Acs",
+            Algorithm::AcsPar => "AcsPar",
+            Algorithm::Custom(name) => name,
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+    /// Names are case insensitive. Anything that isn't a built-in colony
+    /// is taken as the name of a custom one.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let builtin = [
+            Algorithm::Mmas,
+            Algorithm::MmasPar,
+            Algorithm::Acs,
+            Algorithm::AcsPar,
+        ];
+        Ok(builtin
+            .iter()
+            .find(|a| a.name().eq_ignore_ascii_case(s))
+            .cloned()
+            .unwrap_or_else(|| Algorithm::Custom(s.to_owned())))
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Algorithm {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Which ants deposit pheromone in the global pheromone update of MMAS. ACS
+/// always uses the best-so-far ant.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(try_from = "UncheckedDepositStrategy")]
+pub enum DepositStrategy {
+    /// The best ant of the iteration, or every 25 iterations the best ant since
+    /// the last restart
+    #[default]
+    IterationBest,
+    BestSoFar,
+    /// Rank-based: the `w - 1` best ants of the iteration, the r-th of them with
+    /// weight `w - r`, plus the best-so-far ant with weight `w`
+    Ranked(usize),
+}
+
+impl DepositStrategy {
+    /// Checks that a rank-based weight is at least 1.
+    fn check(&self) -> Result<(), String> {
+        match self {
+            DepositStrategy::Ranked(0) => Err("the weight of Ranked must be at least 1".to_owned()),
+            _ => Ok(()),
+        }
     }
 }
 
+/// `DepositStrategy` as written in a run file, before it is checked.
 #[derive(Deserialize)]
+enum UncheckedDepositStrategy {
+    IterationBest,
+    BestSoFar,
+    Ranked(usize),
+}
+
+impl TryFrom<UncheckedDepositStrategy> for DepositStrategy {
+    type Error = String;
+    fn try_from(unchecked: UncheckedDepositStrategy) -> Result<Self, Self::Error> {
+        let deposit = match unchecked {
+            UncheckedDepositStrategy::IterationBest => DepositStrategy::IterationBest,
+            UncheckedDepositStrategy::BestSoFar => DepositStrategy::BestSoFar,
+            UncheckedDepositStrategy::Ranked(weight) => DepositStrategy::Ranked(weight),
+        };
+        deposit.check().map(|_| deposit)
+    }
+}
+
+#[derive(Deserialize, Clone)]
 pub struct AcoParameters {
     #[serde(default = "default_num_ants")]
     pub num_ants: usize,
@@ -36,12 +129,28 @@ pub struct AcoParameters {
     #[serde(default = "default_xi")]
     pub xi: f64,
     pub algorithm: Algorithm,
+    /// Which ants deposit pheromone in MMAS
+    #[serde(default)]
+    pub deposit: DepositStrategy,
+    /// Number of nearest neighbours of each city that keep their own pheromone
+    /// value, with all other edges sharing one. Defaults to keeping every edge,
+    /// unless the instance is too large for full matrices
+    #[serde(default)]
+    pub candidates: Option<usize>,
     /// Maximum number of iterations a colony may run
     #[serde(default = "default_max_iterations")]
     pub max_iterations: usize,
     /// Maximum time in seconds that a colony may run
     #[serde(default = "default_time_limit")]
     pub time_limit: usize,
+    /// Whether the time spent on each phase of an iteration should be
+    /// measured and included in the results
+    #[serde(default)]
+    pub profile: bool,
+    /// Seed for the colony's random number generators. A random one is
+    /// chosen if not given
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
 impl Default for AcoParameters {
@@ -54,9 +163,130 @@ impl Default for AcoParameters {
             q_0: default_q0(),
             xi: default_xi(),
             algorithm: Default::default(),
+            deposit: Default::default(),
+            candidates: None,
             max_iterations: default_max_iterations(),
             time_limit: default_time_limit(),
+            profile: false,
+            seed: None,
+        }
+    }
+}
+
+impl AcoParameters {
+    /// Checks that the parameters can be used on the given instance, as some
+    /// colonies can't store what large instances need, and candidate lists
+    /// and AcsPar only work on symmetric instances.
+    pub fn check_instance(&self, data: &InstanceData) -> Result<(), String> {
+        if !data.is_symmetric() {
+            // candidate matrices and AcsPar update both directions of an edge
+            if self.candidates.is_some() {
+                return Err("candidate lists need a symmetric instance".to_owned());
+            }
+            if self.algorithm == Algorithm::AcsPar {
+                return Err("AcsPar needs a symmetric instance".to_owned());
+            }
+        }
+        if self.algorithm == Algorithm::AcsPar {
+            // it keeps a lock for every edge
+            if self.candidates.is_some() {
+                return Err("AcsPar does not support candidate lists".to_owned());
+            }
+            if data.size > MAX_MATRIX_SIZE {
+                return Err(format!(
+                    "AcsPar stores every edge, so it can't run instances of more than {} cities",
+                    MAX_MATRIX_SIZE
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts building parameters for the given algorithm, with every other
+    /// parameter set to its default value.
+    pub fn builder(algorithm: Algorithm) -> AcoParametersBuilder {
+        AcoParametersBuilder {
+            parameters: AcoParameters {
+                algorithm,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Builder for `AcoParameters`, created with `AcoParameters::builder`.
+pub struct AcoParametersBuilder {
+    parameters: AcoParameters,
+}
+
+impl AcoParametersBuilder {
+    pub fn num_ants(mut self, num_ants: usize) -> Self {
+        self.parameters.num_ants = num_ants;
+        self
+    }
+
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.parameters.alpha = alpha;
+        self
+    }
+
+    pub fn beta(mut self, beta: f64) -> Self {
+        self.parameters.beta = beta;
+        self
+    }
+
+    pub fn evaporation_rate(mut self, evaporation_rate: f64) -> Self {
+        self.parameters.evaporation_rate = evaporation_rate;
+        self
+    }
+
+    pub fn q_0(mut self, q_0: f64) -> Self {
+        self.parameters.q_0 = q_0;
+        self
+    }
+
+    pub fn xi(mut self, xi: f64) -> Self {
+        self.parameters.xi = xi;
+        self
+    }
+
+    pub fn deposit(mut self, deposit: DepositStrategy) -> Self {
+        self.parameters.deposit = deposit;
+        self
+    }
+
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.parameters.max_iterations = max_iterations;
+        self
+    }
+
+    /// Time limit in seconds
+    pub fn time_limit(mut self, time_limit: usize) -> Self {
+        self.parameters.time_limit = time_limit;
+        self
+    }
+
+    pub fn profile(mut self, profile: bool) -> Self {
+        self.parameters.profile = profile;
+        self
+    }
+
+    pub fn candidates(mut self, candidates: usize) -> Self {
+        self.parameters.candidates = Some(candidates);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.parameters.seed = Some(seed);
+        self
+    }
+
+    /// Panics if the deposit strategy is `Ranked` with a weight of 0.
+    pub fn build(self) -> AcoParameters {
+        if let Err(e) = self.parameters.deposit.check() {
+            panic!("invalid parameters: {}", e);
         }
+        self.parameters
     }
 }
 
@@ -82,5 +312,5 @@ fn default_max_iterations() -> usize {
     100000000
 }
 fn default_time_limit() -> usize {
-    std::usize::MAX
+    usize::MAX
 }
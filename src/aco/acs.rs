@@ -1,24 +1,31 @@
 use itertools::Itertools;
+use rand::rngs::StdRng;
 
 use crate::instance_data::InstanceData;
-use crate::util::{self, FloatMatrix};
+use crate::util::EdgeValues;
 
-use super::ant;
-use super::colony::{compute_combined_info, Colony};
+use super::ant::{self, ant_rng};
+use super::checkpoint::ColonyState;
+use super::colony::{
+    choose_seed, compute_combined_info, pheromone_values, recompute_combined_info, Colony,
+};
 use super::AcoParameters;
-use super::Ant;
+use super::{Ant, Population};
 
 pub struct AcsColony<'a> {
     iteration: usize,
+    seed: u64,
     data: &'a InstanceData,
-    pheromones: FloatMatrix,
+    pheromones: EdgeValues,
     /// Heuristic information based on the distance, calculated on initialization
-    heuristic_info: FloatMatrix,
+    heuristic_info: EdgeValues,
     /// Combined pheromone + heuristic information, recalculated every iteration
-    combined_info: FloatMatrix,
+    combined_info: EdgeValues,
     //nn_list: Vec<Vec<usize>>,
     initial_trail: f64,
     parameters: &'a AcoParameters,
+    /// Ants reused by every iteration
+    ants: Vec<Ant>,
 }
 
 impl<'a> Colony<'a> for AcsColony<'a> {
@@ -30,28 +37,30 @@ impl<'a> Colony<'a> for AcsColony<'a> {
         self.iteration
     }
 
-    fn construct_solutions(&mut self) -> Ant {
+    fn construct_solutions(&mut self) -> Population {
         let n_ants = self.parameters.num_ants;
         let data_size = self.data.size;
-        let mut ants_vec = ant::create_ants(n_ants, data_size);
+        let mut rngs: Vec<StdRng> = (0..n_ants)
+            .map(|k| ant_rng(self.seed, self.iteration, k))
+            .collect();
+        // taken out of the colony while they move, as the local update needs it mutably
+        let mut ants = std::mem::take(&mut self.ants);
+        ant::place_ants(&mut ants, data_size, &mut rngs);
         for _ in 0..data_size - 1 {
-            ants_vec = ants_vec
-                .into_iter()
-                .map(|ant| {
-                    let ant =
-                        ant::acs_ant_step(ant, self.data, &self.combined_info, self.parameters);
-                    self.local_pheromone_update(&ant);
-                    ant
-                })
-                .collect();
+            for (ant, rng) in ants.iter_mut().zip(rngs.iter_mut()) {
+                ant::acs_ant_step(ant, rng, self.data, &self.combined_info, self.parameters);
+                self.local_pheromone_update(ant);
+            }
         }
-        for ant in ants_vec.iter_mut() {
-            ant.length += self.data.distances[(ant.get_last(), ant.get_first())];
+        for ant in ants.iter_mut() {
+            ant.length += self.data.distance(ant.get_last(), ant.get_first());
         }
-        ants_vec.into_iter().min_by_key(|a| a.length).unwrap()
+        let population = Population::from_ants(&ants, data_size);
+        self.ants = ants;
+        population
     }
 
-    fn update_pheromones(&mut self, _: &Ant, best_so_far: &Ant) {
+    fn update_pheromones(&mut self, _: &Population, best_so_far: &Ant) {
         let d_tau = 1.0 / best_so_far.length as f64;
         let (alpha, beta, evap_rate) = (
             self.parameters.alpha,
@@ -59,16 +68,47 @@ impl<'a> Colony<'a> for AcsColony<'a> {
             self.parameters.evaporation_rate,
         );
         let coefficient = 1.0 - evap_rate;
-        for (&i, &j) in best_so_far.tour.iter().tuple_windows() {
-            self.pheromones[(i, j)] = coefficient * self.pheromones[(i, j)] + evap_rate * d_tau;
-            self.pheromones[(j, i)] = self.pheromones[(i, j)];
-            self.combined_info[(i, j)] = super::total_value(
-                self.pheromones[(i, j)],
-                self.heuristic_info[(i, j)],
-                alpha,
-                beta,
-            );
-            self.combined_info[(j, i)] = self.combined_info[(i, j)];
+        // including the edge back to the first city
+        let first = best_so_far.get_first();
+        for (i, j) in best_so_far.cities().chain(Some(first)).tuple_windows() {
+            let pheromone = coefficient * self.pheromones.get(i, j) + evap_rate * d_tau;
+            self.pheromones.set(i, j, pheromone);
+            let combined =
+                super::total_value(pheromone, self.heuristic_info.get(i, j), alpha, beta);
+            self.combined_info.set(i, j, combined);
+        }
+    }
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn save_state(&self) -> ColonyState {
+        ColonyState::Acs {
+            iteration: self.iteration,
+            seed: self.seed,
+            pheromones: self.pheromones.clone(),
+        }
+    }
+
+    fn restore_state(&mut self, state: ColonyState) {
+        match state {
+            ColonyState::Acs {
+                iteration,
+                seed,
+                pheromones,
+            } => {
+                self.iteration = iteration;
+                self.seed = seed;
+                self.pheromones = pheromones;
+                recompute_combined_info(
+                    &mut self.combined_info,
+                    &self.pheromones,
+                    &self.heuristic_info,
+                    self.parameters,
+                );
+            }
+            _ => panic!("checkpoint was not saved by an ACS colony"),
         }
     }
 }
@@ -78,14 +118,15 @@ impl<'a> AcsColony<'a> {
         data: &'a InstanceData,
         parameters: &'a AcoParameters,
     ) -> AcsColony<'a> {
-        let nn_tour_length = ant::nearest_neighbour_tour(data, 0);
+        let mut pheromones = pheromone_values(data, parameters, 0.0);
+        let nn_tour_length = ant::nearest_neighbour_tour(data, 0, pheromones.candidates());
         let initial_trail = calculate_initial_values(nn_tour_length, data.size);
-        let pheromones = util::generate_pheromone_matrix(data.size, initial_trail);
-        let (heuristic_info, combined_info) =
-            compute_combined_info(&data.distances, &pheromones, parameters);
+        pheromones.map(|_| initial_trail);
+        let (heuristic_info, combined_info) = compute_combined_info(data, &pheromones, parameters);
 
         Self {
             iteration: 0,
+            seed: choose_seed(parameters),
             data,
             pheromones,
             heuristic_info,
@@ -93,6 +134,9 @@ impl<'a> AcsColony<'a> {
             //nn_list: super::generate_nn_list(data),
             initial_trail,
             parameters,
+            ants: (0..parameters.num_ants)
+                .map(|_| Ant::new(data.size))
+                .collect(),
         }
     }
 
@@ -105,18 +149,13 @@ impl<'a> AcsColony<'a> {
             self.parameters.xi,
         );
         // calculating new pheromone value
-        let modified_old_pherom = (1.0 - xi) * self.pheromones[(i, j)];
+        let modified_old_pherom = (1.0 - xi) * self.pheromones.get(i, j);
         let added_pherom = xi * self.initial_trail;
-        self.pheromones[(i, j)] = modified_old_pherom + added_pherom;
-        self.pheromones[(j, i)] = self.pheromones[(i, j)];
+        let pheromone = modified_old_pherom + added_pherom;
+        self.pheromones.set(i, j, pheromone);
         // update combined info
-        self.combined_info[(i, j)] = super::total_value(
-            self.pheromones[(i, j)],
-            self.heuristic_info[(i, j)],
-            alpha,
-            beta,
-        );
-        self.combined_info[(j, i)] = self.combined_info[(i, j)];
+        let combined = super::total_value(pheromone, self.heuristic_info.get(i, j), alpha, beta);
+        self.combined_info.set(i, j, combined);
     }
 }
 
@@ -1,18 +1,21 @@
 use crate::instance_data::InstanceData;
-use crate::util::{self, FloatMatrix, FloatMatrixSync};
+use crate::util::{self, EdgeValues, FloatMatrix, FloatMatrixSync};
 use itertools::Itertools;
 use parking_lot::Mutex;
+use rand::rngs::StdRng;
 use rayon::prelude::*;
 
-use super::ant;
-use super::colony::{compute_combined_info, Colony};
+use super::ant::{self, ant_rng};
+use super::checkpoint::ColonyState;
+use super::colony::{choose_seed, compute_combined_info, recompute_combined_info, Colony};
 use super::AcoParameters;
-use super::Ant;
+use super::{Ant, Population};
 
 use std::sync::Arc;
 
 pub struct AcsPar<'a> {
     iteration: usize,
+    seed: u64,
     data: &'a InstanceData,
     lock_mutex: Arc<Mutex<()>>,
     pheromones: FloatMatrixSync,
@@ -23,19 +26,29 @@ pub struct AcsPar<'a> {
     //nn_list: Vec<Vec<usize>>,
     initial_trail: f64,
     parameters: &'a AcoParameters,
+    /// Ants reused by every iteration
+    ants: Vec<Ant>,
 }
 
 impl<'a> AcsPar<'a> {
+    /// Panics if the parameters ask for candidate lists, or the instance is too
+    /// large to store every edge, see `AcoParameters::check_instance`.
     pub fn initialize_colony(data: &'a InstanceData, parameters: &'a AcoParameters) -> AcsPar<'a> {
-        let nn_tour_length = ant::nearest_neighbour_tour(data, 0);
+        if let Err(e) = parameters.check_instance(data) {
+            panic!("{}", e);
+        }
+        let nn_tour_length = ant::nearest_neighbour_tour(data, 0, None);
         let initial_trail = calculate_initial_values(nn_tour_length, data.size);
-        let pheromones = util::generate_pheromone_matrix(data.size, initial_trail);
-        let (heuristic_info, combined_info) =
-            compute_combined_info(&data.distances, &pheromones, parameters);
-        let pheromones = util::convert_to_sync(pheromones);
-        let combined_info = util::convert_to_sync(combined_info);
+        // the locks need every edge to be stored, so candidate lists aren't used
+        let pheromones =
+            EdgeValues::Full(util::generate_pheromone_matrix(data.size, initial_trail));
+        let (heuristic_info, combined_info) = compute_combined_info(data, &pheromones, parameters);
+        let pheromones = util::convert_to_sync(full_matrix(pheromones));
+        let heuristic_info = full_matrix(heuristic_info);
+        let combined_info = util::convert_to_sync(full_matrix(combined_info));
         Self {
             iteration: 0,
+            seed: choose_seed(parameters),
             data,
             lock_mutex: Arc::new(Mutex::new(())),
             pheromones,
@@ -44,6 +57,9 @@ impl<'a> AcsPar<'a> {
             //nn_list: super::generate_nn_list(data),
             initial_trail,
             parameters,
+            ants: (0..parameters.num_ants)
+                .map(|_| Ant::new(data.size))
+                .collect(),
         }
     }
 }
@@ -57,46 +73,47 @@ impl<'a> Colony<'a> for AcsPar<'a> {
         self.iteration
     }
 
-    fn construct_solutions(&mut self) -> Ant {
+    fn construct_solutions(&mut self) -> Population {
         let n_ants = self.parameters.num_ants;
         let data_size = self.data.size;
-        let mut ants_vec = ant::create_ants(n_ants, data_size);
+        let mut rngs: Vec<StdRng> = (0..n_ants)
+            .map(|k| ant_rng(self.seed, self.iteration, k))
+            .collect();
+        let mut ants = std::mem::take(&mut self.ants);
+        ant::place_ants(&mut ants, data_size, &mut rngs);
 
         for _ in 0..data_size - 1 {
-            ants_vec = ants_vec
-                .into_par_iter()
-                .map_init(
-                    || {
-                        (
-                            Arc::clone(&self.pheromones),
-                            Arc::clone(&self.combined_info),
-                            Arc::clone(&self.lock_mutex),
-                        )
-                    },
-                    |(pheromones, combined_info, mutex), ant| {
-                        let ant =
-                            ant::acs_ant_step(ant, self.data, &self.combined_info, self.parameters);
-                        local_pheromone_update(
-                            mutex,
-                            pheromones,
-                            &self.heuristic_info,
-                            combined_info,
-                            self.parameters,
-                            self.initial_trail,
-                            &ant,
-                        );
-                        ant
-                    },
-                )
-                .collect();
+            ants.par_iter_mut().zip(rngs.par_iter_mut()).for_each_init(
+                || {
+                    (
+                        Arc::clone(&self.pheromones),
+                        Arc::clone(&self.combined_info),
+                        Arc::clone(&self.lock_mutex),
+                    )
+                },
+                |(pheromones, combined_info, mutex), (ant, rng)| {
+                    ant::acs_ant_step(ant, rng, self.data, &self.combined_info, self.parameters);
+                    local_pheromone_update(
+                        mutex,
+                        pheromones,
+                        &self.heuristic_info,
+                        combined_info,
+                        self.parameters,
+                        self.initial_trail,
+                        ant,
+                    );
+                },
+            );
         }
-        for ant in ants_vec.iter_mut() {
-            ant.length += self.data.distances[(ant.get_last(), ant.get_first())];
+        for ant in ants.iter_mut() {
+            ant.length += self.data.distance(ant.get_last(), ant.get_first());
         }
-        ants_vec.into_iter().min_by_key(|a| a.length).unwrap()
+        let population = Population::from_ants(&ants, data_size);
+        self.ants = ants;
+        population
     }
 
-    fn update_pheromones(&mut self, _: &Ant, best_so_far: &Ant) {
+    fn update_pheromones(&mut self, _: &Population, best_so_far: &Ant) {
         let d_tau = 1.0 / best_so_far.length as f64;
         let (alpha, beta, evap_rate) = (
             self.parameters.alpha,
@@ -104,7 +121,9 @@ impl<'a> Colony<'a> for AcsPar<'a> {
             self.parameters.evaporation_rate,
         );
         let coefficient = 1.0 - evap_rate;
-        for (&i, &j) in best_so_far.tour.iter().tuple_windows() {
+        // including the edge back to the first city
+        let first = best_so_far.get_first();
+        for (i, j) in best_so_far.cities().chain(Some(first)).tuple_windows() {
             // this method is always run on the main thread, while no slave threads
             // are executing, so there's no need to worry about avoiding deadlocks
             // by using the mutex before getting the locks
@@ -118,6 +137,49 @@ impl<'a> Colony<'a> for AcsPar<'a> {
             *comb_ji = *comb_ij;
         }
     }
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn save_state(&self) -> ColonyState {
+        ColonyState::Acs {
+            iteration: self.iteration,
+            seed: self.seed,
+            pheromones: EdgeValues::Full(util::convert_from_sync(&self.pheromones)),
+        }
+    }
+
+    fn restore_state(&mut self, state: ColonyState) {
+        match state {
+            ColonyState::Acs {
+                iteration,
+                seed,
+                pheromones,
+            } => {
+                let mut combined_info =
+                    EdgeValues::Full(util::convert_from_sync(&self.combined_info));
+                let heuristic_info = EdgeValues::Full(self.heuristic_info.clone());
+                recompute_combined_info(
+                    &mut combined_info,
+                    &pheromones,
+                    &heuristic_info,
+                    self.parameters,
+                );
+                self.iteration = iteration;
+                self.seed = seed;
+                self.pheromones = util::convert_to_sync(full_matrix(pheromones));
+                self.combined_info = util::convert_to_sync(full_matrix(combined_info));
+            }
+            _ => panic!("checkpoint was not saved by an ACS colony"),
+        }
+    }
+}
+
+fn full_matrix(values: EdgeValues) -> FloatMatrix {
+    values
+        .into_full()
+        .expect("AcsPar only works with full pheromone matrices")
 }
 
 fn calculate_initial_values(nn_tour_length: u32, num_nodes: usize) -> f64 {
@@ -1,22 +1,30 @@
 use super::aco_parameters::AcoParameters;
 use crate::instance_data::InstanceData;
-use crate::util::ColonyInfoMatrix;
-use indexmap::IndexSet;
-use rand::{thread_rng, Rng};
+use crate::util::{CandidateLists, ColonyInfoMatrix};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde_derive::{Deserialize, Serialize};
 
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct Ant {
-    pub tour: IndexSet<usize>,
+    pub tour: Vec<u32>,
     pub length: u32,
     pub curr_city: usize,
+    /// 1.0 for cities that are not part of the tour yet and 0.0 for the rest,
+    /// used as a mask by the construction kernels. Only kept while the ant is
+    /// building a tour, ants taken from a `Population` or a checkpoint leave it
+    /// empty.
+    #[serde(skip)]
+    unvisited: Vec<f64>,
 }
 
 impl Ant {
-    fn new(data_size: usize) -> Self {
+    pub fn new(data_size: usize) -> Self {
         Ant {
-            tour: IndexSet::with_capacity(data_size),
+            tour: Vec::with_capacity(data_size),
             length: 0,
             curr_city: 0,
+            unvisited: vec![1.0; data_size],
         }
     }
 
@@ -26,139 +34,200 @@ impl Ant {
         a
     }
 
-    // pub fn drain_to_result(&mut self) -> AntResult {
-    //     let tour = self.tour.drain(..).collect();
-    //     self.length = 0;
-    //     self.curr_city = 0;
+    /// Creates an ant that has completed the given tour, including the edge back
+    /// to the first city in its length.
+    pub fn from_tour(data: &InstanceData, tour: &[usize]) -> Self {
+        let mut ant = Self::new_on_city(data.size, tour[0]);
+        for &city in &tour[1..] {
+            ant.insert(city, data.distance(ant.curr_city, city));
+        }
+        ant.length += data.distance(ant.get_last(), ant.get_first());
+        ant
+    }
 
-    //     AntResult {
-    //         tour,
-    //         length: self.length,
-    //     }
-    // }
+    /// Creates a finished ant from a tour and its already known length, without
+    /// tracking visited cities.
+    pub(super) fn completed(tour: Vec<u32>, length: u32) -> Self {
+        Ant {
+            curr_city: *tour.last().expect("empty tour") as usize,
+            tour,
+            length,
+            unvisited: Vec::new(),
+        }
+    }
+
+    /// Clears the tour and places the ant on a new city, keeping its allocations
+    /// so it can be reused for the next iteration.
+    pub fn reset(&mut self, data_size: usize, starting_city: usize) {
+        self.tour.clear();
+        self.unvisited.clear();
+        self.unvisited.resize(data_size, 1.0);
+        self.length = 0;
+        self.insert(starting_city, 0);
+    }
 
     fn insert(&mut self, new_node: usize, connection_length: u32) {
         //println!("{},{}: {} + {}", self.curr_city, new_node, self.length, connection_length );
         self.curr_city = new_node;
-        self.tour.insert(new_node);
+        self.tour.push(new_node as u32);
+        self.unvisited[new_node] = 0.0;
         self.length += connection_length;
     }
 
+    /// Cities of the tour, in order.
+    pub fn cities(&self) -> impl Iterator<Item = usize> + Clone + '_ {
+        self.tour.iter().map(|&c| c as usize)
+    }
+
     pub fn get_first(&self) -> usize {
-        *self.tour.get_index(0).expect("get first")
+        *self.tour.first().expect("get first") as usize
     }
 
     pub fn get_last(&self) -> usize {
-        *self.tour.get_index(self.tour.len() - 1).expect("get_last")
+        *self.tour.last().expect("get_last") as usize
     }
 
     pub fn get_last_arc(&self) -> (usize, usize) {
-        (
-            *self.tour.get_index(self.tour.len() - 2).expect("last arc"),
-            self.get_last(),
-        )
+        (self.tour[self.tour.len() - 2] as usize, self.get_last())
     }
 }
 
-/// Form nearest neighbour tour given a starting city and return its length
-pub fn nearest_neighbour_tour(data: &InstanceData, starting_city: usize) -> u32 {
-    let mut tour = IndexSet::with_capacity(data.size);
-    tour.insert(starting_city);
+/// Form nearest neighbour tour given a starting city and return its length. If
+/// candidate lists are given, the closest unvisited candidate is used and all
+/// cities are only searched once every candidate was visited.
+pub fn nearest_neighbour_tour(
+    data: &InstanceData,
+    starting_city: usize,
+    candidates: Option<&CandidateLists>,
+) -> u32 {
+    let mut unvisited = vec![1.0; data.size];
+    unvisited[starting_city] = 0.0;
     let mut curr = starting_city;
-    let mut next = starting_city;
-    let mut next_length = std::u32::MAX;
     let mut length = 0;
-    while tour.len() != data.size {
-        for (i, v) in data.distances.row(curr).iter().enumerate() {
-            if !tour.contains(&i) && v < &next_length {
-                next = i;
-                next_length = *v;
-            }
-        }
-
-        tour.insert(next);
-        length += next_length;
+    for _ in 1..data.size {
+        let next = candidates
+            .and_then(|lists| {
+                lists
+                    .of(curr)
+                    .iter()
+                    .map(|&c| c as usize)
+                    .find(|&c| unvisited[c] != 0.0)
+            })
+            .unwrap_or_else(|| nearest_unvisited(data, curr, &unvisited));
+        unvisited[next] = 0.0;
+        length += data.distance(curr, next);
         curr = next;
-        next_length = std::u32::MAX;
     }
     // Include edge between last and initial node in the length
-    length += data.distances[(tour.pop().unwrap(), *tour.get_index(0).unwrap())];
+    length += data.distance(curr, starting_city);
     length
 }
 
+/// Closest city to `curr_city` that wasn't visited yet. Used when the colony
+/// doesn't have information for any of the remaining cities.
+fn nearest_unvisited(data: &InstanceData, curr_city: usize, unvisited: &[f64]) -> usize {
+    (0..data.size)
+        .filter(|&i| unvisited[i] != 0.0)
+        .min_by_key(|&i| data.distance(curr_city, i))
+        .expect("every city was visited")
+}
+
 /// Chooses an unvisited city to go to with the highest total combined heuristic+pheromone
 /// information. Returns the index of that city.
 fn choose_best_next<T: ColonyInfoMatrix>(
+    data: &InstanceData,
     curr_city: usize,
-    visited: &IndexSet<usize>,
+    unvisited: &[f64],
     combined_info: &T,
 ) -> usize {
-    combined_info.filtered_row_max(curr_city, visited)
+    combined_info
+        .best_unvisited(curr_city, unvisited)
+        .unwrap_or_else(|| nearest_unvisited(data, curr_city, unvisited))
 }
 
 /// Chooses an unvisited city to go to using the proportional rule defined in the literature.
-/// Returns the index of that city.
+/// Returns the index of that city. If there is no information on any of the unvisited
+/// cities (when only candidate edges are stored), goes to the closest one.
 fn choose_probabilistically<T: ColonyInfoMatrix>(
+    data: &InstanceData,
     curr_city: usize,
-    visited: &IndexSet<usize>,
+    unvisited: &[f64],
     combined_info: &T,
     rng: &mut impl Rng,
 ) -> usize {
-    let (unvisited, weights) = combined_info.unvisited_weights(curr_city, visited);
-    let mut random_v: f64 = rng.gen::<f64>() * weights.iter().sum::<f64>();
-    for (city, weight) in unvisited.iter().zip(weights) {
-        random_v -= weight;
-        if random_v < 0.0 {
-            return *city;
-        }
-    }
-    unreachable!()
+    let r: f64 = rng.gen();
+    combined_info
+        .roulette(curr_city, unvisited, r)
+        .unwrap_or_else(|| nearest_unvisited(data, curr_city, unvisited))
+}
+
+/// Creates the random number generator used by a single ant in a given iteration.
+///
+/// Deriving it from the colony's seed, instead of using `thread_rng`, makes runs
+/// reproducible regardless of how ants are distributed among threads, and means
+/// the only RNG state that has to be stored in a checkpoint is the seed itself.
+pub fn ant_rng(seed: u64, iteration: usize, ant_index: usize) -> StdRng {
+    // each component goes through the mixer before being combined with the next,
+    // so different triples don't cancel each other out as they would with xor
+    let mixed = splitmix64(splitmix64(splitmix64(seed) ^ iteration as u64) ^ ant_index as u64);
+    StdRng::seed_from_u64(mixed)
 }
 
-pub fn mmas_ant<T: ColonyInfoMatrix>(data: &InstanceData, combined_info: &T) -> Ant {
-    let mut rng = thread_rng();
+/// The output function of the SplitMix64 generator, which spreads every bit of
+/// its input over the whole result.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Builds a complete MMAS tour with the given ant, discarding any tour it had
+/// before. The ant's allocations are reused, so the same ants can be kept
+/// across iterations.
+pub fn mmas_ant<T: ColonyInfoMatrix>(
+    ant: &mut Ant,
+    data: &InstanceData,
+    combined_info: &T,
+    rng: &mut impl Rng,
+) {
     let starting_city = rng.gen_range(0, data.size);
-    let mut ant = Ant::new_on_city(data.size, starting_city);
-    //ant.length = 0;
+    ant.reset(data.size, starting_city);
     for _ in 0..data.size - 1 {
-        let next_city = choose_probabilistically(ant.curr_city, &ant.tour, combined_info, &mut rng);
+        let next_city =
+            choose_probabilistically(data, ant.curr_city, &ant.unvisited, combined_info, rng);
         //TODO use nn_list to aid performance
-        //let next_city = choose_best_next(ant.curr_city, &ant.tour, combined_info);
-        ant.insert(next_city, data.distances[(ant.curr_city, next_city)]);
+        //let next_city = choose_best_next(data, ant.curr_city, &ant.unvisited, combined_info);
+        ant.insert(next_city, data.distance(ant.curr_city, next_city));
     }
     // Include edge between last and initial node in the length
-    ant.length += data.distances[(ant.get_last(), ant.get_first())];
-    ant
+    ant.length += data.distance(ant.get_last(), ant.get_first());
 }
 
-/// Generates a vec of `Ant`s. To be used with ACS so it can step each ant individually and
-/// update pheromones locally. Ants are placed on a random initial city.
-pub fn create_ants(num_ants: usize, data_size: usize) -> Vec<Ant> {
-    let mut v = Vec::with_capacity(num_ants);
-    for _ in 0..num_ants {
-        let starting_city = thread_rng().gen_range(0, data_size);
-        v.push(Ant::new_on_city(data_size, starting_city));
+/// Places each ant on a random initial city, to be used with ACS so it can step
+/// each ant individually and update pheromones locally. Each ant uses the RNG at
+/// the same position.
+pub fn place_ants(ants: &mut [Ant], data_size: usize, rngs: &mut [impl Rng]) {
+    for (ant, rng) in ants.iter_mut().zip(rngs.iter_mut()) {
+        ant.reset(data_size, rng.gen_range(0, data_size));
     }
-    v
 }
 
-/// A single step for an `Ant` in the ACS algorithm.
-/// Returns an ant that has moved a step further
+/// A single step for an `Ant` in the ACS algorithm, moving it to its next city.
 pub fn acs_ant_step<T: ColonyInfoMatrix>(
-    mut ant: Ant,
+    ant: &mut Ant,
+    rng: &mut impl Rng,
     data: &InstanceData,
     combined_info: &T,
     parameters: &AcoParameters,
-) -> Ant {
+) {
     // note: acs assumes an aplha value of 1 in all cases
-    let mut rng = thread_rng();
     let next_city = if rng.gen_bool(parameters.q_0) {
         // get max heuristic info
-        choose_best_next(ant.curr_city, &ant.tour, combined_info)
+        choose_best_next(data, ant.curr_city, &ant.unvisited, combined_info)
     } else {
         //get probabilistic
-        choose_probabilistically(ant.curr_city, &ant.tour, combined_info, &mut rng)
+        choose_probabilistically(data, ant.curr_city, &ant.unvisited, combined_info, rng)
     };
-    ant.insert(next_city, data.distances[(ant.curr_city, next_city)]);
-    ant
+    ant.insert(next_city, data.distance(ant.curr_city, next_city));
 }
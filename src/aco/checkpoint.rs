@@ -0,0 +1,105 @@
+use super::ant::Ant;
+use super::ResultLog;
+use crate::util::EdgeValues;
+use serde_derive::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where and how often a running colony should save its state.
+pub struct CheckpointConfig {
+    pub path: PathBuf,
+    pub interval: Duration,
+}
+
+/// Everything a colony needs to continue from where it stopped. Combined
+/// information matrices are not stored, as they can be recomputed from the
+/// pheromones and the instance.
+#[derive(Serialize, Deserialize)]
+pub enum ColonyState {
+    Mmas {
+        iteration: usize,
+        seed: u64,
+        pheromones: EdgeValues,
+        trail_min: f64,
+        trail_max: f64,
+        restart_ant: Option<Ant>,
+        restart_iter: usize,
+        /// Missing from checkpoints saved before it was stored
+        #[serde(default)]
+        restarts: usize,
+    },
+    /// Used by both `AcsColony` and `AcsPar`.
+    Acs {
+        iteration: usize,
+        seed: u64,
+        pheromones: EdgeValues,
+    },
+    /// For colonies defined outside this crate, which may store their state
+    /// however they like.
+    Custom(serde_json::Value),
+}
+
+impl ColonyState {
+    /// Pheromones of the colony, unless it is a custom colony.
+    pub fn pheromones(&self) -> Option<&EdgeValues> {
+        match self {
+            ColonyState::Mmas { pheromones, .. } | ColonyState::Acs { pheromones, .. } => {
+                Some(pheromones)
+            }
+            ColonyState::Custom(_) => None,
+        }
+    }
+}
+
+/// A snapshot of a run, saved periodically by `run_colony` and loaded when
+/// resuming.
+#[derive(Deserialize)]
+pub struct Checkpoint {
+    /// Time the run had been executing for when the checkpoint was made
+    pub elapsed: Duration,
+    pub colony: ColonyState,
+    pub result_log: ResultLog,
+}
+
+/// Borrowed version of `Checkpoint`, so saving doesn't require cloning the
+/// whole result log.
+#[derive(Serialize)]
+struct CheckpointRef<'a> {
+    elapsed: Duration,
+    colony: &'a ColonyState,
+    result_log: &'a ResultLog,
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> io::Result<Checkpoint> {
+        let f = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(f))?)
+    }
+}
+
+/// Writes a checkpoint to a temporary file first and then renames it, so an
+/// interruption while saving doesn't destroy the previous checkpoint.
+pub fn save(
+    path: &Path,
+    elapsed: Duration,
+    colony: &ColonyState,
+    result_log: &ResultLog,
+) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        let checkpoint = CheckpointRef {
+            elapsed,
+            colony,
+            result_log,
+        };
+        serde_json::to_writer(&mut writer, &checkpoint)?;
+        writer.flush()?;
+    }
+    fs::rename(tmp_path, path)
+}
@@ -1,58 +1,92 @@
 use super::ant::Ant;
-use super::AcoParameters;
-use crate::util::{FloatMatrix, IntegerMatrix};
+use super::checkpoint::ColonyState;
+use super::{AcoParameters, PhaseTimes, Population};
+use crate::instance_data::{InstanceData, MAX_MATRIX_SIZE};
+use crate::util::{self, CandidateMatrix, EdgeValues};
+use rand::Rng;
+use std::sync::Arc;
 
 pub trait Colony<'a> {
     fn new_iteration(&mut self);
     fn iteration(&self) -> usize;
-    fn construct_solutions(&mut self) -> Ant;
-    fn update_pheromones(&mut self, best_this_iter: &Ant, best_so_far: &Ant);
+    /// Builds a tour with every ant of the colony, returning all of them.
+    fn construct_solutions(&mut self) -> Population;
+    /// Updates the pheromones with the tours of the iteration. Each colony decides
+    /// which of them (and whether the best-so-far tour) deposit pheromone.
+    fn update_pheromones(&mut self, population: &Population, best_so_far: &Ant);
+    /// Times of the phases the colony measures internally, if any (e.g. restarts).
+    /// `run_colony` measures the phases corresponding to the methods above.
+    fn phase_times(&self) -> Option<&PhaseTimes> {
+        None
+    }
+    /// Number of times the pheromone trails were reinitialized, for colonies that do so.
+    fn restarts(&self) -> usize {
+        0
+    }
+    /// Seed from which all of the colony's random decisions are derived.
+    fn seed(&self) -> u64;
+    fn save_state(&self) -> ColonyState;
+    /// Replaces the colony's state with one from a checkpoint. Panics if the
+    /// state was saved by a different kind of colony.
+    fn restore_state(&mut self, state: ColonyState);
 }
 
-/// Generates and computes the heuristic info matrix and combined heuristic+pheromone matrix.
-/// Returns a (heuristic, combined) tuple of FloatMatrixs.
-/// Only to be used on initialization, use `recompute_combined_info` to fully update a combined
-/// info matrix in-place.
-pub fn compute_combined_info(
-    distances: &IntegerMatrix,
-    pheromones: &FloatMatrix,
-    parameters: &AcoParameters,
-) -> (FloatMatrix, FloatMatrix) {
-    let mut heuristic_info = FloatMatrix::with_element(distances.width(), 0.0);
-    let mut combined_info = FloatMatrix::with_element(distances.width(), 0.0);
-    for i in 0..distances.width() {
-        for j in 0..i {
-            heuristic_info[(i, j)] = super::heuristic(distances, i, j);
-            heuristic_info[(j, i)] = heuristic_info[(i, j)];
-            combined_info[(i, j)] = super::total_value(
-                pheromones[(i, j)],
-                heuristic_info[(i, j)],
-                parameters.alpha,
-                parameters.beta,
-            );
-            combined_info[(j, i)] = combined_info[(i, j)];
+/// Returns the seed given in the parameters, or a random one if none was given.
+pub fn choose_seed(parameters: &AcoParameters) -> u64 {
+    parameters.seed.unwrap_or_else(|| rand::thread_rng().gen())
+}
+
+/// Number of candidates of each city used for instances too large for full
+/// matrices, if the parameters don't give one.
+const DEFAULT_CANDIDATES: usize = 20;
+
+/// Creates the storage for a colony's pheromones, with every edge set to `value`.
+/// Only candidate edges are stored if the parameters ask for candidate lists or
+/// the instance is too large to store every edge, otherwise symmetric instances
+/// store each edge once.
+pub fn pheromone_values(data: &InstanceData, parameters: &AcoParameters, value: f64) -> EdgeValues {
+    let candidates = match parameters.candidates {
+        Some(k) => Some(k),
+        None if data.size > MAX_MATRIX_SIZE => Some(DEFAULT_CANDIDATES),
+        None => None,
+    };
+    match candidates {
+        Some(k) => {
+            let lists = Arc::new(data.nearest_neighbours(k));
+            EdgeValues::Candidates(CandidateMatrix::new(lists, value))
         }
+        None if data.is_symmetric() => {
+            EdgeValues::Symmetric(util::generate_symmetric_pheromone_matrix(data.size, value))
+        }
+        None => EdgeValues::Full(util::generate_pheromone_matrix(data.size, value)),
     }
+}
+
+/// Generates and computes the heuristic info and combined heuristic+pheromone
+/// values, for the same edges stored in `pheromones`.
+/// Returns a (heuristic, combined) tuple.
+/// Only to be used on initialization, use `recompute_combined_info` to fully update
+/// combined info in-place.
+pub fn compute_combined_info(
+    data: &InstanceData,
+    pheromones: &EdgeValues,
+    parameters: &AcoParameters,
+) -> (EdgeValues, EdgeValues) {
+    let heuristic_info = pheromones.with_same_edges(|i, j| super::heuristic(data, i, j));
+    let mut combined_info = heuristic_info.clone();
+    recompute_combined_info(&mut combined_info, pheromones, &heuristic_info, parameters);
     (heuristic_info, combined_info)
 }
 
 pub fn recompute_combined_info(
-    combined_info: &mut FloatMatrix,
-    pheromones: &FloatMatrix,
-    heuristic_info: &FloatMatrix,
+    combined_info: &mut EdgeValues,
+    pheromones: &EdgeValues,
+    heuristic_info: &EdgeValues,
     parameters: &AcoParameters,
 ) {
-    for i in 0..combined_info.width() {
-        for j in 0..i {
-            combined_info[(i, j)] = super::total_value(
-                pheromones[(i, j)],
-                heuristic_info[(i, j)],
-                parameters.alpha,
-                parameters.beta,
-            );
-            combined_info[(j, i)] = combined_info[(j, i)];
-        }
-    }
+    combined_info.combine(pheromones, heuristic_info, |pheromone, heuristic| {
+        super::total_value(pheromone, heuristic, parameters.alpha, parameters.beta)
+    });
 }
 
 //fn generate_nn_list(data: &InstanceData, list_size: usize) -> Vec<Vec<usize>> {
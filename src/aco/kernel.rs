@@ -0,0 +1,154 @@
+//! Building blocks for choosing the next city during tour construction.
+//!
+//! Rather than filtering out visited cities, a row of combined information is
+//! multiplied by a mask holding 1.0 for cities that can still be visited and 0.0
+//! for the rest. The loops work on fixed-size blocks with no branches, so the
+//! compiler can vectorise them.
+
+/// Number of elements processed together. Eight `f64`s fill an AVX-512 register
+/// or two AVX ones.
+const LANES: usize = 8;
+
+/// Sum of `values[i] * mask[i]`.
+pub fn masked_sum(values: &[f64], mask: &[f64]) -> f64 {
+    let mask = &mask[..values.len()];
+    let value_blocks = values.chunks_exact(LANES);
+    let mask_blocks = mask.chunks_exact(LANES);
+    let remainder: f64 = value_blocks
+        .remainder()
+        .iter()
+        .zip(mask_blocks.remainder())
+        .map(|(v, m)| v * m)
+        .sum();
+    let mut sums = [0.0; LANES];
+    for (v, m) in value_blocks.zip(mask_blocks) {
+        for lane in 0..LANES {
+            sums[lane] += v[lane] * m[lane];
+        }
+    }
+    sums.iter().sum::<f64>() + remainder
+}
+
+/// Roulette wheel selection: returns the first index where the running sum of
+/// `values[i] * mask[i]` goes over `target`. Masked elements are never chosen.
+///
+/// If the sum of every element is not over `target`, returns what is left of
+/// `target` after subtracting them, so the selection can continue over another
+/// part of the row.
+pub fn masked_select(values: &[f64], mask: &[f64], target: f64) -> Result<usize, f64> {
+    let mask = &mask[..values.len()];
+    let mut remaining = target;
+    let value_blocks = values.chunks_exact(LANES);
+    let mask_blocks = mask.chunks_exact(LANES);
+    let remainder_start = values.len() - value_blocks.remainder().len();
+    let (value_remainder, mask_remainder) = (value_blocks.remainder(), mask_blocks.remainder());
+    for (block, (v, m)) in value_blocks.zip(mask_blocks).enumerate() {
+        let mut products = [0.0; LANES];
+        for lane in 0..LANES {
+            products[lane] = v[lane] * m[lane];
+        }
+        let block_sum: f64 = products.iter().sum();
+        if remaining < block_sum {
+            // the chosen element is in this block, unless rounding says otherwise
+            // in which case the search just moves on to the next block
+            match select_in(&products, remaining) {
+                Ok(lane) => return Ok(block * LANES + lane),
+                Err(left) => remaining = left,
+            }
+        } else {
+            remaining -= block_sum;
+        }
+    }
+    let mut products = [0.0; LANES];
+    for (p, (v, m)) in products
+        .iter_mut()
+        .zip(value_remainder.iter().zip(mask_remainder))
+    {
+        *p = v * m;
+    }
+    select_in(&products[..value_remainder.len()], remaining).map(|i| remainder_start + i)
+}
+
+/// Chooses an index with probability proportional to `values[i] * mask[i]`,
+/// given `r` uniformly distributed in [0, 1). Returns `None` if every element is
+/// masked (or 0).
+pub fn roulette(values: &[f64], mask: &[f64], r: f64) -> Option<usize> {
+    let total = masked_sum(values, mask);
+    if total <= 0.0 {
+        return None;
+    }
+    // if rounding makes the target unreachable, it would have been the last element
+    masked_select(values, mask, r * total)
+        .ok()
+        .or_else(|| last_unmasked(values, mask))
+}
+
+/// Index of the last element with `values[i] * mask[i]` over 0.
+pub fn last_unmasked(values: &[f64], mask: &[f64]) -> Option<usize> {
+    values.iter().zip(mask).rposition(|(v, m)| v * m > 0.0)
+}
+
+/// Scalar roulette wheel selection over already masked weights.
+fn select_in(weights: &[f64], mut remaining: f64) -> Result<usize, f64> {
+    for (i, &weight) in weights.iter().enumerate() {
+        if weight > 0.0 && remaining < weight {
+            return Ok(i);
+        }
+        remaining -= weight;
+    }
+    Err(remaining)
+}
+
+/// Largest value of `values[i] * mask[i]`, with its index. Returns `None` if
+/// every element is masked (or 0).
+pub fn masked_max(values: &[f64], mask: &[f64]) -> Option<(usize, f64)> {
+    let mask = &mask[..values.len()];
+    let value_blocks = values.chunks_exact(LANES);
+    let mask_blocks = mask.chunks_exact(LANES);
+    let mut maxes = [0.0f64; LANES];
+    for (v, m) in value_blocks.clone().zip(mask_blocks.clone()) {
+        for lane in 0..LANES {
+            maxes[lane] = maxes[lane].max(v[lane] * m[lane]);
+        }
+    }
+    let max = value_blocks
+        .remainder()
+        .iter()
+        .zip(mask_blocks.remainder())
+        .map(|(v, m)| v * m)
+        .fold(maxes.iter().cloned().fold(0.0, f64::max), f64::max);
+    if max <= 0.0 {
+        return None;
+    }
+    // finding where the maximum is in a second pass keeps the first one free of
+    // index bookkeeping
+    values
+        .iter()
+        .zip(mask)
+        .position(|(v, m)| v * m == max)
+        .map(|i| (i, max))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn kernels_skip_masked_elements() {
+        let values: Vec<f64> = (1..=19).map(f64::from).collect();
+        let mut mask = vec![1.0; values.len()];
+        mask[18] = 0.0;
+        mask[3] = 0.0;
+        let expected_sum: f64 = (1..=18).sum::<u32>() as f64 - 4.0;
+        assert_eq!(masked_sum(&values, &mask), expected_sum);
+        assert_eq!(masked_max(&values, &mask), Some((17, 18.0)));
+        // 1 + 2 + 3 = 6, the 4th element is masked, so 6.5 falls on the 5th
+        assert_eq!(masked_select(&values, &mask, 6.5), Ok(4));
+        assert_eq!(masked_select(&values, &mask, 0.0), Ok(0));
+        assert_eq!(masked_select(&values, &mask, expected_sum - 0.5), Ok(17));
+        assert_eq!(masked_select(&values, &mask, expected_sum + 1.0), Err(1.0));
+        assert_eq!(masked_max(&values, &[0.0; 19]), None);
+        assert_eq!(roulette(&values, &[0.0; 19], 0.5), None);
+        assert_eq!(roulette(&values, &mask, 0.0), Some(0));
+    }
+}
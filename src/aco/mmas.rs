@@ -1,21 +1,25 @@
 use itertools::Itertools;
 use rayon::prelude::*;
 
-use super::ant::{self, mmas_ant};
-use super::colony::{compute_combined_info, recompute_combined_info, Colony};
-use super::{AcoParameters, Ant};
+use super::ant::{self, ant_rng, mmas_ant};
+use super::checkpoint::ColonyState;
+use super::colony::{
+    choose_seed, compute_combined_info, pheromone_values, recompute_combined_info, Colony,
+};
+use super::{AcoParameters, Ant, DepositStrategy, Phase, PhaseTimes, Population};
 use crate::instance_data::InstanceData;
-use crate::util::{self, FloatMatrix};
+use crate::util::{EdgeDeposits, EdgeValues};
 
 pub struct MmasColony<'a> {
     iteration: usize,
+    seed: u64,
     parallel: bool,
     data: &'a InstanceData,
-    pheromones: FloatMatrix,
+    pheromones: EdgeValues,
     /// Heuristic information based on the distance, calculated on initialization
-    heuristic_info: FloatMatrix,
+    heuristic_info: EdgeValues,
     /// Combined pheromone + heuristic information, recalculated every iteration
-    combined_info: FloatMatrix,
+    combined_info: EdgeValues,
     //nn_list: Vec<Vec<usize>>,
     /// Maximum pheromone value for MMAS. This is calculated by the colony.
     pub trail_max: f64,
@@ -24,22 +28,25 @@ pub struct MmasColony<'a> {
     parameters: &'a AcoParameters,
     restart_ant: Option<Ant>,
     restart_iter: usize,
+    restarts: usize,
+    phase_times: PhaseTimes,
+    /// Ants reused by every iteration
+    ants: Vec<Ant>,
+    /// Pheromone deposited on the current iteration
+    deposits: EdgeDeposits,
 }
 
 impl<'a> Colony<'a> for MmasColony<'a> {
     fn new_iteration(&mut self) {
         self.iteration += 1;
         if self.iteration - self.restart_iter >= 150 {
-            println!("restart");
+            crate::logger::log_restart(self.iteration);
+            let start = self.phase_times.start();
             self.restart_ant = None;
             self.reinitialize_trails();
-            recompute_combined_info(
-                &mut self.combined_info,
-                &self.pheromones,
-                &self.heuristic_info,
-                self.parameters,
-            );
             self.restart_iter = self.iteration;
+            self.restarts += 1;
+            self.phase_times.record(Phase::Restart, start);
         }
     }
 
@@ -47,48 +54,150 @@ impl<'a> Colony<'a> for MmasColony<'a> {
         self.iteration
     }
 
-    fn construct_solutions(&mut self) -> Ant {
+    fn construct_solutions(&mut self) -> Population {
         //println!("new construction {}", self.iteration);
-        let range = 0..self.parameters.num_ants;
+        let (seed, iteration) = (self.seed, self.iteration);
+        let (data, combined_info) = (self.data, &self.combined_info);
+        let build_ant = |(k, ant): (usize, &mut Ant)| {
+            let mut rng = ant_rng(seed, iteration, k);
+            mmas_ant(ant, data, combined_info, &mut rng)
+        };
         if self.parallel {
-            range
-                .into_par_iter()
-                .map(|_| mmas_ant(self.data, &self.combined_info))
-                .min_by_key(|a| a.length)
-                .unwrap()
+            self.ants.par_iter_mut().enumerate().for_each(build_ant);
         } else {
-            range
-                .into_iter()
-                .map(|_| mmas_ant(self.data, &self.combined_info))
-                .min_by_key(|a| a.length)
-                .unwrap()
+            self.ants.iter_mut().enumerate().for_each(build_ant);
         }
+        Population::from_ants(&self.ants, data.size)
     }
 
-    fn update_pheromones(&mut self, best_this_iter: &Ant, best_so_far: &Ant) {
+    fn update_pheromones(&mut self, population: &Population, best_so_far: &Ant) {
+        let best_index = population.best_index();
         if self.restart_ant.is_none()
-            || best_this_iter.length < self.restart_ant.as_ref().unwrap().length
+            || population.length(best_index) < self.restart_ant.as_ref().unwrap().length
         {
-            self.restart_ant = Some(best_this_iter.clone());
+            self.restart_ant = Some(population.ant(best_index));
             self.restart_iter = self.iteration;
         }
         let evap_rate = self.parameters.evaporation_rate;
         let (min, max) = calculate_bounding_values(best_so_far.length, self.data.size, evap_rate);
         self.trail_min = min;
         self.trail_max = max;
-        evaporate(&mut self.pheromones, evap_rate);
-        let ant_to_use = match self.iteration % 25 {
-            0 => &self.restart_ant.as_ref().unwrap_or(best_so_far),
-            _ => best_this_iter,
-        };
-        global_update_pheromones(&mut self.pheromones, ant_to_use);
-        self.check_trail_limits();
-        recompute_combined_info(
-            &mut self.combined_info,
-            &self.pheromones,
-            &self.heuristic_info,
-            self.parameters,
-        );
+        let deposits = &mut self.deposits;
+        deposits.clear();
+        match self.parameters.deposit {
+            DepositStrategy::IterationBest => match self.iteration % 25 {
+                0 => {
+                    let ant = self.restart_ant.as_ref().unwrap_or(best_so_far);
+                    global_update_pheromones(deposits, ant, 1.0);
+                }
+                _ => {
+                    let (tour, length) =
+                        (population.tour(best_index), population.length(best_index));
+                    deposit(
+                        deposits,
+                        tour.iter().map(|&c| c as usize),
+                        1.0 / length as f64,
+                    );
+                }
+            },
+            DepositStrategy::BestSoFar => {
+                global_update_pheromones(deposits, best_so_far, 1.0);
+            }
+            DepositStrategy::Ranked(weight) => {
+                // the r-th best ant of the iteration deposits with weight (w - r),
+                // and the best-so-far ant with weight w
+                for (rank, &i) in population.ranked().iter().take(weight - 1).enumerate() {
+                    let ant_weight = (weight - rank - 1) as f64;
+                    let tour = population.tour(i).iter().map(|&c| c as usize);
+                    deposit(deposits, tour, ant_weight / population.length(i) as f64);
+                }
+                global_update_pheromones(deposits, best_so_far, weight as f64);
+            }
+        }
+        if self.parallel {
+            let start = self.phase_times.start();
+            // a single parallel sweep over the pheromones does every step
+            let parameters = self.parameters;
+            self.pheromones.par_update(
+                |pheromone| pheromone * (1.0 - evap_rate),
+                &self.deposits,
+                |pheromone| pheromone.max(min).min(max),
+                &mut self.combined_info,
+                &self.heuristic_info,
+                |pheromone, heuristic| {
+                    super::total_value(pheromone, heuristic, parameters.alpha, parameters.beta)
+                },
+            );
+            self.phase_times.record(Phase::RecomputeCombinedInfo, start);
+        } else {
+            evaporate(&mut self.pheromones, evap_rate);
+            self.deposits.apply(&mut self.pheromones);
+            self.check_trail_limits();
+            let start = self.phase_times.start();
+            recompute_combined_info(
+                &mut self.combined_info,
+                &self.pheromones,
+                &self.heuristic_info,
+                self.parameters,
+            );
+            self.phase_times.record(Phase::RecomputeCombinedInfo, start);
+        }
+    }
+
+    fn phase_times(&self) -> Option<&PhaseTimes> {
+        Some(&self.phase_times)
+    }
+
+    fn restarts(&self) -> usize {
+        self.restarts
+    }
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn save_state(&self) -> ColonyState {
+        ColonyState::Mmas {
+            iteration: self.iteration,
+            seed: self.seed,
+            pheromones: self.pheromones.clone(),
+            trail_min: self.trail_min,
+            trail_max: self.trail_max,
+            restart_ant: self.restart_ant.clone(),
+            restart_iter: self.restart_iter,
+            restarts: self.restarts,
+        }
+    }
+
+    fn restore_state(&mut self, state: ColonyState) {
+        match state {
+            ColonyState::Mmas {
+                iteration,
+                seed,
+                pheromones,
+                trail_min,
+                trail_max,
+                restart_ant,
+                restart_iter,
+                restarts,
+            } => {
+                self.iteration = iteration;
+                self.seed = seed;
+                self.pheromones = pheromones;
+                self.trail_min = trail_min;
+                self.trail_max = trail_max;
+                self.restart_ant = restart_ant;
+                self.restart_iter = restart_iter;
+                self.restarts = restarts;
+                recompute_combined_info(
+                    &mut self.combined_info,
+                    &self.pheromones,
+                    &self.heuristic_info,
+                    self.parameters,
+                );
+            }
+            _ => panic!("checkpoint was not saved by an MMAS colony"),
+        }
     }
 }
 
@@ -98,15 +207,16 @@ impl<'a> MmasColony<'a> {
         parameters: &'a AcoParameters,
         parallel: bool,
     ) -> MmasColony<'a> {
-        let nn_tour_length = ant::nearest_neighbour_tour(data, 0);
+        let mut pheromones = pheromone_values(data, parameters, 0.0);
+        let nn_tour_length = ant::nearest_neighbour_tour(data, 0, pheromones.candidates());
         let (trail_min, trail_max) =
             calculate_bounding_values(nn_tour_length, data.size, parameters.evaporation_rate);
-        let pheromones = util::generate_pheromone_matrix(data.size, trail_max);
-        let (heuristic_info, combined_info) =
-            compute_combined_info(&data.distances, &pheromones, parameters);
+        pheromones.map(|_| trail_max);
+        let (heuristic_info, combined_info) = compute_combined_info(data, &pheromones, parameters);
 
         Self {
             iteration: 0,
+            seed: choose_seed(parameters),
             parallel,
             data,
             pheromones,
@@ -118,30 +228,43 @@ impl<'a> MmasColony<'a> {
             parameters,
             restart_ant: None,
             restart_iter: 1,
+            restarts: 0,
+            phase_times: PhaseTimes::new(parameters.profile),
+            ants: (0..parameters.num_ants)
+                .map(|_| Ant::new(data.size))
+                .collect(),
+            deposits: EdgeDeposits::new(),
         }
     }
 
     fn check_trail_limits(&mut self) {
-        for i in 0..self.pheromones.width() {
-            for j in 0..i {
-                if self.pheromones[(i, j)] < self.trail_min {
-                    self.pheromones[(i, j)] = self.trail_min;
-                    self.pheromones[(j, i)] = self.trail_min;
-                }
-                if self.pheromones[(i, j)] > self.trail_max {
-                    self.pheromones[(i, j)] = self.trail_max;
-                    self.pheromones[(j, i)] = self.trail_max;
-                }
-            }
-        }
+        let (min, max) = (self.trail_min, self.trail_max);
+        self.pheromones.map(|pheromone| pheromone.max(min).min(max));
     }
 
+    /// Sets every pheromone to `trail_max`, also updating the combined information.
     fn reinitialize_trails(&mut self) {
-        for i in 0..self.pheromones.width() {
-            for j in 0..i {
-                self.pheromones[(i, j)] = self.trail_max;
-                self.pheromones[(j, i)] = self.trail_max;
-            }
+        let max = self.trail_max;
+        if self.parallel {
+            let parameters = self.parameters;
+            self.pheromones.par_update(
+                |_| max,
+                &EdgeDeposits::new(),
+                |pheromone| pheromone,
+                &mut self.combined_info,
+                &self.heuristic_info,
+                |pheromone, heuristic| {
+                    super::total_value(pheromone, heuristic, parameters.alpha, parameters.beta)
+                },
+            );
+        } else {
+            self.pheromones.map(|_| max);
+            recompute_combined_info(
+                &mut self.combined_info,
+                &self.pheromones,
+                &self.heuristic_info,
+                self.parameters,
+            );
         }
     }
 }
@@ -158,19 +281,22 @@ fn calculate_bounding_values(
     (trail_min, trail_max)
 }
 
-fn evaporate(pheromones: &mut FloatMatrix, evap_rate: f64) {
-    for i in 0..pheromones.width() {
-        for j in 0..i {
-            pheromones[(i, j)] = (1.0 - evap_rate) * pheromones[(i, j)];
-            pheromones[(j, i)] = pheromones[(i, j)];
-        }
-    }
+fn evaporate(pheromones: &mut EdgeValues, evap_rate: f64) {
+    pheromones.map(|pheromone| pheromone * (1.0 - evap_rate));
+}
+
+/// Deposits pheromone on the tour of an ant, proportionally to the quality
+/// of the tour and the given weight.
+fn global_update_pheromones(deposits: &mut EdgeDeposits, ant: &Ant, weight: f64) {
+    let d_tau = weight / (ant.length as f64);
+    deposit(deposits, ant.cities(), d_tau);
 }
 
-fn global_update_pheromones(pheromones: &mut FloatMatrix, ant: &Ant) {
-    let d_tau = 1.0 / (ant.length as f64);
-    for (&i, &j) in ant.tour.iter().tuple_windows() {
-        pheromones[(i, j)] = pheromones[(i, j)] + d_tau;
-        pheromones[(j, i)] = pheromones[(i, j)];
+/// Adds `amount` of pheromone to every edge of a tour, including the one
+/// back to the first city.
+fn deposit(deposits: &mut EdgeDeposits, tour: impl Iterator<Item = usize> + Clone, amount: f64) {
+    let first = tour.clone().next();
+    for (i, j) in tour.chain(first).tuple_windows() {
+        deposits.add(i, j, amount);
     }
 }
@@ -2,76 +2,247 @@ mod aco_parameters;
 mod acs;
 mod acspar;
 mod ant;
+mod checkpoint;
 mod colony;
+pub mod kernel;
 mod mmas;
+mod observer;
+mod phase_times;
+mod population;
+mod registry;
 mod result_log;
 
 use crate::instance_data::InstanceData;
-use crate::util::IntegerMatrix;
-
-pub use self::aco_parameters::{AcoParameters, Algorithm};
-pub use self::ant::Ant;
-use self::colony::Colony;
-pub use self::result_log::{ResultLog, TimestampedResult};
-
-/// Sets up and runs the algorithm.
-pub fn run_aco(data: &InstanceData, parameters: &AcoParameters) -> ResultLog {
-    let algorithm = &parameters.algorithm;
-    // Colony could be turned into a trait object to avoid core repetition here,
-    // but I'm not sure if it's worth it.
-    // This structure was initially chosen to avoid the slight overhead of using trait
-    // objects in the first place (though I think it'd ultimately not be that big of a deal.
-    // Haven't actually tested it, though).
-    // Since this is the only place that would be affected by a change in terms of
-    // code length and readibility, I decided to leave it as is. If more colonies
-    // were to be implemented, I'd probably change to using a trait object
-    match *algorithm {
-        Algorithm::Mmas => {
-            let colony = mmas::MmasColony::initialize_colony(data, parameters, false);
-            run_colony(colony, parameters.max_iterations, parameters.time_limit)
-        }
-        Algorithm::MmasPar => {
-            let colony = mmas::MmasColony::initialize_colony(data, parameters, true);
-            run_colony(colony, parameters.max_iterations, parameters.time_limit)
-        }
-        Algorithm::Acs => {
-            let colony = acs::AcsColony::initialize_colony(data, parameters);
-            run_colony(colony, parameters.max_iterations, parameters.time_limit)
-        }
-        Algorithm::AcsPar => {
-            let colony = acspar::AcsPar::initialize_colony(data, parameters);
-            run_colony(colony, parameters.max_iterations, parameters.time_limit)
-        }
+use crate::logger;
+use crate::progress::RunProgress;
+use crate::timer::Stopwatch;
+
+pub use self::aco_parameters::{AcoParameters, AcoParametersBuilder, Algorithm, DepositStrategy};
+pub use self::ant::{ant_rng, mmas_ant, Ant};
+pub use self::checkpoint::{Checkpoint, CheckpointConfig, ColonyState};
+pub use self::colony::{compute_combined_info, pheromone_values, recompute_combined_info, Colony};
+pub use self::observer::{Control, Observer};
+pub use self::phase_times::{Phase, PhaseTimes};
+pub use self::population::Population;
+pub use self::registry::{ColonyConstructor, ColonyRegistry};
+pub use self::result_log::ResultLog;
+pub use crate::util::{ColonyInfoMatrix, EdgeValues, TourError};
+
+/// Optional extras for a run, on top of what is described by its parameters.
+#[derive(Default)]
+pub struct RunOptions {
+    /// Where to periodically save the colony's state. It is also saved when an
+    /// observer stops the run.
+    pub checkpoint: Option<CheckpointConfig>,
+    /// State to continue from instead of starting over
+    pub resume: Option<Checkpoint>,
+    /// Where to report the run's progress after each iteration
+    pub progress: Option<RunProgress>,
+    /// Hooks called at each stage of the run
+    pub observers: Vec<Box<dyn Observer>>,
+    /// Tour to start from, e.g. a known good tour, treated as if the colony had
+    /// built it on the first iteration. Ignored when resuming.
+    pub initial_tour: Option<Ant>,
+}
+
+/// Sets up and runs the algorithm, which must be one of the built-in colonies.
+/// Use `run_aco_with_registry` to run colonies defined elsewhere.
+pub fn run_aco(data: &InstanceData, parameters: &AcoParameters, options: RunOptions) -> ResultLog {
+    run_aco_with_registry(
+        &ColonyRegistry::with_builtin_colonies(),
+        data,
+        parameters,
+        options,
+    )
+}
+
+/// Sets up the colony registered under the name of the algorithm in the
+/// parameters and runs it.
+///
+/// Panics if no colony is registered under that name.
+pub fn run_aco_with_registry(
+    registry: &ColonyRegistry,
+    data: &InstanceData,
+    parameters: &AcoParameters,
+    options: RunOptions,
+) -> ResultLog {
+    if let Err(e) = parameters.check_instance(data) {
+        panic!("{}", e);
+    }
+    let name = parameters.algorithm.name();
+    // started before the colony is initialized, so that time counts towards the time limit
+    let stopwatch = Stopwatch::new();
+    let mut colony = registry.create(name, data, parameters).unwrap_or_else(|| {
+        panic!(
+            "no colony registered as {}, known colonies are: {}",
+            name,
+            registry.names().join(", ")
+        )
+    });
+    let result_log = run_colony(colony.as_mut(), parameters, stopwatch, options);
+    if let Err(e) = result_log.verify(data) {
+        logger::log_invalid_tour(&e);
     }
+    result_log
 }
 
 fn run_colony<'a>(
-    mut colony: impl Colony<'a>,
-    max_iterations: usize,
-    max_time: usize,
+    colony: &mut dyn Colony<'a>,
+    parameters: &AcoParameters,
+    mut stopwatch: Stopwatch,
+    options: RunOptions,
 ) -> ResultLog {
+    let (max_iterations, max_time) = (parameters.max_iterations, parameters.time_limit);
     let mut result_log = ResultLog::new(max_iterations);
-    while !check_termination(&colony, max_iterations, max_time) {
+    let mut phase_times = PhaseTimes::new(parameters.profile);
+    let mut initial_tour = options.initial_tour;
+    if let Some(saved) = options.resume {
+        initial_tour = None;
+        colony.restore_state(saved.colony);
+        result_log = saved.result_log;
+        phase_times = result_log.phase_times.clone();
+        stopwatch = Stopwatch::starting_from(saved.elapsed);
+    }
+    result_log.seed = colony.seed();
+    let mut observers = options.observers;
+    for observer in observers.iter_mut() {
+        observer.on_start(result_log.seed);
+    }
+    let mut last_checkpoint = stopwatch.elapsed();
+    let mut stop = false;
+    while !stop && !check_termination(colony, &stopwatch, max_iterations, max_time) {
+        let restarts = colony.restarts();
+        let start = phase_times.start();
         colony.new_iteration();
-        let result = colony.construct_solutions();
-        update_stats(result, &mut result_log, colony.iteration());
-        colony.update_pheromones(result_log.latest_tour(), result_log.best_tour());
+        phase_times.record(Phase::NewIteration, start);
+        let iteration = colony.iteration();
+        stop |= notify(&mut observers, |o| {
+            o.on_iteration_start(iteration, stopwatch.elapsed())
+        });
+        if colony.restarts() > restarts {
+            stop |= notify(&mut observers, |o| o.on_restart(iteration));
+        }
+
+        let start = phase_times.start();
+        let mut population = colony.construct_solutions();
+        phase_times.record(Phase::ConstructSolutions, start);
+        if let Some(ant) = initial_tour.take() {
+            population.push_ant(&ant);
+        }
+        stop |= notify(&mut observers, |o| {
+            o.on_solutions_constructed(iteration, &mut population)
+        });
+
+        update_stats(
+            population.ant(population.best_index()),
+            &mut result_log,
+            iteration,
+            &stopwatch,
+        );
+        log_progress(&result_log);
+        let latest = result_log.latest();
+        if latest.is_new_best {
+            stop |= notify(&mut observers, |o| {
+                o.on_new_best(iteration, result_log.best_tour(), latest.timestamp)
+            });
+        }
+        if let Some(progress) = &options.progress {
+            progress.update(
+                colony.iteration(),
+                result_log.best_length(),
+                colony.restarts(),
+                stopwatch.elapsed(),
+            );
+        }
+
+        let start = phase_times.start();
+        colony.update_pheromones(&population, result_log.best_tour());
+        phase_times.record(Phase::UpdatePheromones, start);
+        let state = || colony.save_state();
+        stop |= notify(&mut observers, |o| {
+            o.on_pheromones_updated(iteration, &state)
+        });
+
+        if let Some(config) = &options.checkpoint {
+            let elapsed = stopwatch.elapsed();
+            if elapsed - last_checkpoint >= config.interval {
+                result_log.phase_times = merged_phase_times(colony, &phase_times);
+                checkpoint::save(&config.path, elapsed, &colony.save_state(), &result_log)
+                    .expect("failed writing checkpoint");
+                last_checkpoint = elapsed;
+            }
+        }
+    }
+    result_log.phase_times = merged_phase_times(colony, &phase_times);
+    result_log.stopped = stop;
+    if let (true, Some(config)) = (stop, &options.checkpoint) {
+        // so that no work is lost when the run is resumed
+        checkpoint::save(
+            &config.path,
+            stopwatch.elapsed(),
+            &colony.save_state(),
+            &result_log,
+        )
+        .expect("failed writing checkpoint");
+    }
+    if let Some(progress) = &options.progress {
+        progress.finish();
+    }
+    for observer in observers.iter_mut() {
+        observer.on_termination(&result_log);
     }
     result_log
 }
 
-fn check_termination<'a>(colony: &impl Colony<'a>, max_iterations: usize, max_time: usize) -> bool {
-    colony.iteration() > max_iterations || crate::timer::elapsed().as_secs() >= max_time as u64
+/// Calls `f` on every observer. Returns whether any of them asked to stop the run.
+fn notify(
+    observers: &mut [Box<dyn Observer>],
+    mut f: impl FnMut(&mut dyn Observer) -> Control,
+) -> bool {
+    // every observer is notified, even after one of them asks to stop
+    let mut stop = false;
+    for observer in observers.iter_mut() {
+        if f(observer.as_mut()) == Control::Stop {
+            stop = true;
+        }
+    }
+    stop
+}
+
+/// Phases that only happen inside some colonies are timed by the colony itself,
+/// so they are combined with the ones timed by `run_colony` here.
+fn merged_phase_times<'a>(colony: &dyn Colony<'a>, phase_times: &PhaseTimes) -> PhaseTimes {
+    let mut merged = phase_times.clone();
+    if let Some(inner) = colony.phase_times() {
+        merged.merge(inner);
+    }
+    merged
+}
+
+fn check_termination<'a>(
+    colony: &dyn Colony<'a>,
+    stopwatch: &Stopwatch,
+    max_iterations: usize,
+    max_time: usize,
+) -> bool {
+    colony.iteration() > max_iterations || stopwatch.elapsed().as_secs() >= max_time as u64
 }
 
-fn update_stats(best_this_iter: Ant, result_log: &mut ResultLog, iteration: usize) {
-    //let best_this_iter = find_best(iter_results);
-    result_log.push(best_this_iter.to_owned(), iteration);
+fn update_stats(
+    best_this_iter: Ant,
+    result_log: &mut ResultLog,
+    iteration: usize,
+    stopwatch: &Stopwatch,
+) {
+    result_log.push(best_this_iter.to_owned(), iteration, stopwatch.elapsed());
 }
 
-#[allow(dead_code)]
-fn find_best<'a>(results: &'a [Ant]) -> &'a Ant {
-    results.iter().min_by_key(|x| x.length).unwrap()
+fn log_progress(result_log: &ResultLog) {
+    let latest = result_log.latest();
+    if latest.is_new_best {
+        logger::log_new_best(latest.iteration, result_log.best_tour());
+    }
+    logger::log_iteration(latest.iteration, latest.length, result_log.best_length());
 }
 
 /// Function to calculate the total weight of a given connection, combining its current
@@ -81,6 +252,416 @@ pub fn total_value(pheromone: f64, heuristic: f64, alpha: f64, beta: f64) -> f64
     pheromone.powf(alpha) * heuristic.powf(beta)
 }
 
-fn heuristic(distances: &IntegerMatrix, i: usize, j: usize) -> f64 {
-    1.0 / ((distances[(i, j)] as f64) + 0.1)
+fn heuristic(data: &InstanceData, i: usize, j: usize) -> f64 {
+    1.0 / ((data.distance(i, j) as f64) + 0.1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Eight cities evenly spread along the sides of a square, so the optimal tour
+    /// just follows the square.
+    fn square_instance() -> InstanceData {
+        let coordinates = [
+            (0.0, 0.0),
+            (5.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 5.0),
+            (10.0, 10.0),
+            (5.0, 10.0),
+            (0.0, 10.0),
+            (0.0, 5.0),
+        ];
+        InstanceData::from_coordinates(&coordinates)
+    }
+
+    #[test]
+    fn all_algorithms_find_square_tour() {
+        let data = square_instance();
+        for algorithm in [
+            Algorithm::Mmas,
+            Algorithm::MmasPar,
+            Algorithm::Acs,
+            Algorithm::AcsPar,
+        ]
+        .iter()
+        .cloned()
+        {
+            let parameters = AcoParameters::builder(algorithm)
+                .num_ants(8)
+                .max_iterations(50)
+                .seed(1)
+                .build();
+            let results = run_aco(&data, &parameters, RunOptions::default());
+            assert_eq!(results.best_length(), 40, "{:?}", parameters.algorithm);
+            assert_eq!(results.best_tour().tour.len(), data.size);
+        }
+    }
+
+    #[test]
+    fn mmas_deposit_strategies_find_square_tour() {
+        let data = square_instance();
+        for deposit in [DepositStrategy::BestSoFar, DepositStrategy::Ranked(4)]
+            .iter()
+            .cloned()
+        {
+            let parameters = AcoParameters::builder(Algorithm::Mmas)
+                .num_ants(8)
+                .max_iterations(50)
+                .deposit(deposit)
+                .seed(1)
+                .build();
+            let results = run_aco(&data, &parameters, RunOptions::default());
+            assert_eq!(results.best_length(), 40, "{:?}", parameters.deposit);
+        }
+    }
+
+    #[test]
+    fn ranked_deposit_needs_a_positive_weight() {
+        let parameters: Result<AcoParameters, _> =
+            serde_json::from_str(r#"{ "algorithm": "mmas", "deposit": { "Ranked": 0 } }"#);
+        assert!(parameters.is_err());
+        let parameters: AcoParameters =
+            serde_json::from_str(r#"{ "algorithm": "mmas", "deposit": { "Ranked": 1 } }"#).unwrap();
+        assert_eq!(parameters.deposit, DepositStrategy::Ranked(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "weight of Ranked")]
+    fn builder_rejects_ranked_deposit_without_weight() {
+        AcoParameters::builder(Algorithm::Mmas)
+            .deposit(DepositStrategy::Ranked(0))
+            .build();
+    }
+
+    #[test]
+    fn acs_global_update_reaches_closing_edge() {
+        let data = square_instance();
+        let best = Ant::from_tour(&data, &[0, 1, 2, 3, 4, 5, 6, 7]);
+        for algorithm in [Algorithm::Acs, Algorithm::AcsPar].iter().cloned() {
+            let parameters = AcoParameters::builder(algorithm)
+                .num_ants(1)
+                .seed(1)
+                .build();
+            let registry = ColonyRegistry::with_builtin_colonies();
+            let mut colony = registry
+                .create(parameters.algorithm.name(), &data, &parameters)
+                .unwrap();
+            let state = colony.save_state();
+            let before = state.pheromones().unwrap().get(7, 0);
+            colony.update_pheromones(
+                &Population::from_ants(std::slice::from_ref(&best), data.size),
+                &best,
+            );
+            let state = colony.save_state();
+            let after = state.pheromones().unwrap();
+            assert!(after.get(7, 0) > before, "{:?}", parameters.algorithm);
+            assert_eq!(
+                after.get(7, 0),
+                after.get(0, 1),
+                "{:?}",
+                parameters.algorithm
+            );
+        }
+    }
+
+    #[test]
+    fn candidate_lists_find_square_tour() {
+        let data = square_instance();
+        for algorithm in [Algorithm::Mmas, Algorithm::Acs].iter().cloned() {
+            let parameters = AcoParameters::builder(algorithm)
+                .num_ants(8)
+                .max_iterations(50)
+                .candidates(3)
+                .seed(1)
+                .build();
+            let results = run_aco(&data, &parameters, RunOptions::default());
+            assert_eq!(results.best_length(), 40, "{:?}", parameters.algorithm);
+        }
+    }
+
+    #[test]
+    fn acspar_rejects_candidate_lists() {
+        let data = square_instance();
+        let parameters = AcoParameters::builder(Algorithm::AcsPar)
+            .candidates(3)
+            .build();
+        assert!(parameters.check_instance(&data).is_err());
+        let parameters = AcoParameters::builder(Algorithm::Acs).candidates(3).build();
+        assert!(parameters.check_instance(&data).is_ok());
+    }
+
+    #[test]
+    fn asymmetric_instances_are_solved_in_the_cheap_direction() {
+        // going to the next city costs 1, going back 100 and anything else 50
+        let size = 8;
+        let matrix: Vec<Vec<u32>> = (0..size)
+            .map(|i| {
+                (0..size)
+                    .map(|j| match (j + size - i) % size {
+                        0 => 0,
+                        1 => 1,
+                        d if d == size - 1 => 100,
+                        _ => 50,
+                    })
+                    .collect()
+            })
+            .collect();
+        let data = InstanceData::from_distance_matrix(&matrix);
+        assert!(!data.is_symmetric());
+        for algorithm in [Algorithm::Mmas, Algorithm::MmasPar, Algorithm::Acs].iter() {
+            let parameters = AcoParameters::builder(algorithm.clone())
+                .num_ants(8)
+                .max_iterations(100)
+                .seed(3)
+                .build();
+            let results = run_aco(&data, &parameters, RunOptions::default());
+            assert_eq!(results.best_length(), size as u32, "{:?}", algorithm);
+        }
+        let parameters = AcoParameters::builder(Algorithm::Acs).candidates(3).build();
+        assert!(parameters.check_instance(&data).is_err());
+        let parameters = AcoParameters::builder(Algorithm::AcsPar).build();
+        assert!(parameters.check_instance(&data).is_err());
+    }
+
+    #[test]
+    fn parallel_mmas_matches_sequential() {
+        // points on a parabola, so that the instance has a single optimal tour but
+        // distances are not all alike
+        let coordinates: Vec<(f64, f64)> = (0..30)
+            .map(|i| (i as f64 * 7.0 % 31.0, (i * i) as f64 % 23.0))
+            .collect();
+        let data = InstanceData::from_coordinates(&coordinates);
+        for candidates in [None, Some(5)].iter().cloned() {
+            let results: Vec<Vec<u32>> = [Algorithm::Mmas, Algorithm::MmasPar]
+                .iter()
+                .map(|algorithm| {
+                    let mut builder = AcoParameters::builder(algorithm.clone())
+                        .num_ants(10)
+                        .max_iterations(60)
+                        .deposit(DepositStrategy::Ranked(4))
+                        .seed(3);
+                    if let Some(k) = candidates {
+                        builder = builder.candidates(k);
+                    }
+                    let results = run_aco(&data, &builder.build(), RunOptions::default());
+                    results.log.iter().map(|r| r.length).collect()
+                })
+                .collect();
+            assert_eq!(results[0], results[1], "{:?}", candidates);
+        }
+    }
+
+    #[test]
+    fn initial_tour_is_recorded_on_first_iteration() {
+        let coordinates: Vec<(f64, f64)> = (0..30)
+            .map(|i| (i as f64 * 7.0 % 31.0, (i * i) as f64 % 23.0))
+            .collect();
+        let data = InstanceData::from_coordinates(&coordinates);
+        let long_run = AcoParameters::builder(Algorithm::Mmas)
+            .num_ants(10)
+            .max_iterations(200)
+            .seed(3)
+            .build();
+        let good_tour = run_aco(&data, &long_run, RunOptions::default())
+            .best_tour()
+            .clone();
+
+        let parameters = AcoParameters::builder(Algorithm::Acs)
+            .num_ants(1)
+            .max_iterations(1)
+            .seed(1)
+            .build();
+        let unseeded = run_aco(&data, &parameters, RunOptions::default());
+        // the seeded tour has to be better than what a single ant finds, or the
+        // test would pass without it having any effect
+        assert!(unseeded.best_length() > good_tour.length);
+        let options = RunOptions {
+            initial_tour: Some(good_tour.clone()),
+            ..Default::default()
+        };
+        let results = run_aco(&data, &parameters, options);
+        assert_eq!(results.log[0].length, good_tour.length);
+        assert_eq!(results.best_tour().tour, good_tour.tour);
+    }
+
+    #[test]
+    fn ant_rngs_differ_for_nearby_triples() {
+        use rand::Rng;
+        // with a plain xor of the components these all gave the same stream
+        let triples = [(0, 1, 0), (1, 0, 1), (1 << 32, 1, 0), (0, 0, 0)];
+        let mut firsts: Vec<u64> = triples
+            .iter()
+            .map(|&(seed, iteration, ant)| ant_rng(seed, iteration, ant).gen())
+            .collect();
+        firsts.sort_unstable();
+        firsts.dedup();
+        assert_eq!(firsts.len(), triples.len());
+    }
+
+    #[test]
+    fn resumed_run_matches_uninterrupted_run() {
+        let coordinates: Vec<(f64, f64)> = (0..12)
+            .map(|i| (i as f64 * 7.0 % 13.0, (i * i) as f64 % 11.0))
+            .collect();
+        let data = InstanceData::from_coordinates(&coordinates);
+        // long enough for MMAS to restart its trails before the interruption
+        let parameters = AcoParameters::builder(Algorithm::Mmas)
+            .num_ants(5)
+            .max_iterations(400)
+            .seed(5)
+            .profile(true)
+            .build();
+        let uninterrupted = run_aco(&data, &parameters, RunOptions::default());
+
+        let dir = crate::test_support::test_dir("resume");
+        let path = dir.join("run.checkpoint");
+        let options = RunOptions {
+            checkpoint: Some(CheckpointConfig {
+                path: path.clone(),
+                interval: std::time::Duration::from_secs(0),
+            }),
+            observers: vec![Box::new(StopAfter {
+                iterations: 250,
+                ants_seen: Rc::new(Cell::new(0)),
+            })],
+            ..Default::default()
+        };
+        run_aco(&data, &parameters, options);
+        let checkpoint = Checkpoint::load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(checkpoint.result_log.log.len(), 250);
+        let interrupted_times = checkpoint.result_log.phase_times.clone();
+        assert_eq!(interrupted_times.calls(Phase::ConstructSolutions), 250);
+
+        let mut colony = ColonyRegistry::with_builtin_colonies()
+            .create("mmas", &data, &parameters)
+            .unwrap();
+        colony.restore_state(checkpoint.colony);
+        let restarts = colony.restarts();
+        assert!(restarts > 0);
+        let saved = colony.save_state();
+        assert_eq!(colony.restarts(), restarts);
+
+        let options = RunOptions {
+            resume: Some(Checkpoint {
+                elapsed: checkpoint.elapsed,
+                colony: saved,
+                result_log: checkpoint.result_log,
+            }),
+            ..Default::default()
+        };
+        let resumed = run_aco(&data, &parameters, options);
+        let lengths =
+            |results: &ResultLog| -> Vec<u32> { results.log.iter().map(|r| r.length).collect() };
+        assert_eq!(lengths(&resumed), lengths(&uninterrupted));
+        assert_eq!(resumed.seed, uninterrupted.seed);
+        assert!(!resumed.stopped);
+        // the times of the resumed part are added to the ones before the interruption
+        let (resumed_times, uninterrupted_times) =
+            (&resumed.phase_times, &uninterrupted.phase_times);
+        for &phase in [
+            Phase::NewIteration,
+            Phase::ConstructSolutions,
+            Phase::Restart,
+        ]
+        .iter()
+        {
+            assert_eq!(
+                resumed_times.calls(phase),
+                uninterrupted_times.calls(phase),
+                "{:?}",
+                phase
+            );
+            assert!(resumed_times.total(phase) >= interrupted_times.total(phase));
+        }
+        assert!(resumed_times.calls(Phase::Restart) > interrupted_times.calls(Phase::Restart));
+    }
+
+    /// Colony that always builds the tour visiting cities in index order.
+    struct InOrderColony<'a> {
+        iteration: usize,
+        data: &'a InstanceData,
+    }
+
+    impl<'a> Colony<'a> for InOrderColony<'a> {
+        fn new_iteration(&mut self) {
+            self.iteration += 1;
+        }
+        fn iteration(&self) -> usize {
+            self.iteration
+        }
+        fn construct_solutions(&mut self) -> Population {
+            let tour: Vec<usize> = (0..self.data.size).collect();
+            Population::from_ants(&[Ant::from_tour(self.data, &tour)], self.data.size)
+        }
+        fn update_pheromones(&mut self, _: &Population, _: &Ant) {}
+        fn seed(&self) -> u64 {
+            0
+        }
+        fn save_state(&self) -> ColonyState {
+            ColonyState::Custom(serde_json::json!({ "iteration": self.iteration }))
+        }
+        fn restore_state(&mut self, _: ColonyState) {}
+    }
+
+    #[test]
+    fn registered_colony_is_selected_by_name() {
+        let data = square_instance();
+        let mut registry = ColonyRegistry::with_builtin_colonies();
+        registry.register("InOrder", |data, _| {
+            Box::new(InOrderColony { iteration: 0, data })
+        });
+        let parameters: AcoParameters =
+            serde_json::from_str(r#"{ "algorithm": "inorder", "max_iterations": 3 }"#).unwrap();
+        assert!(registry.contains("INORDER"));
+        assert!(!registry.contains("inordr"));
+        assert_eq!(
+            parameters.algorithm,
+            Algorithm::Custom("inorder".to_owned())
+        );
+        let results = run_aco_with_registry(&registry, &data, &parameters, RunOptions::default());
+        assert!(results.log.iter().all(|r| r.length == 40));
+    }
+
+    /// Counts the ants it sees and stops the run after a number of iterations.
+    struct StopAfter {
+        iterations: usize,
+        ants_seen: Rc<Cell<usize>>,
+    }
+
+    impl Observer for StopAfter {
+        fn on_solutions_constructed(&mut self, iteration: usize, ants: &mut Population) -> Control {
+            self.ants_seen.set(self.ants_seen.get() + ants.len());
+            if iteration >= self.iterations {
+                Control::Stop
+            } else {
+                Control::Continue
+            }
+        }
+    }
+
+    #[test]
+    fn observer_sees_all_ants_and_stops_run() {
+        let data = square_instance();
+        let parameters = AcoParameters::builder(Algorithm::Mmas)
+            .num_ants(5)
+            .seed(1)
+            .build();
+        let ants_seen = Rc::new(Cell::new(0));
+        let options = RunOptions {
+            observers: vec![Box::new(StopAfter {
+                iterations: 3,
+                ants_seen: Rc::clone(&ants_seen),
+            })],
+            ..Default::default()
+        };
+        let results = run_aco(&data, &parameters, options);
+        assert_eq!(results.log.len(), 3);
+        assert_eq!(ants_seen.get(), 15);
+        assert!(results.stopped);
+    }
 }
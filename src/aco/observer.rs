@@ -0,0 +1,53 @@
+use super::{Ant, ColonyState, Population, ResultLog};
+use std::time::Duration;
+
+/// What an observer wants the run to do after one of its callbacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    /// Ends the run once the current iteration's results have been recorded.
+    Stop,
+}
+
+/// Hooks called by `run_colony` at each stage of a run, e.g. to record custom
+/// metrics or to stop the run because of some external signal. Every method
+/// does nothing by default.
+pub trait Observer {
+    /// Called once before the first iteration, with the seed of the colony's
+    /// random number generators.
+    fn on_start(&mut self, _seed: u64) {}
+
+    fn on_iteration_start(&mut self, _iteration: usize, _elapsed: Duration) -> Control {
+        Control::Continue
+    }
+
+    /// Called with every ant built in an iteration, before the best of them is
+    /// recorded and used to update the pheromones. Ants added here (e.g. solutions
+    /// found by other means) are treated as if the colony had built them.
+    fn on_solutions_constructed(&mut self, _iteration: usize, _ants: &mut Population) -> Control {
+        Control::Continue
+    }
+
+    fn on_new_best(&mut self, _iteration: usize, _best: &Ant, _elapsed: Duration) -> Control {
+        Control::Continue
+    }
+
+    /// Called after the colony updated its pheromones. `state` returns the
+    /// current state of the colony, which copies all of its pheromones, so it
+    /// should only be called when needed.
+    fn on_pheromones_updated(
+        &mut self,
+        _iteration: usize,
+        _state: &dyn Fn() -> ColonyState,
+    ) -> Control {
+        Control::Continue
+    }
+
+    /// Called when the colony reinitializes its pheromone trails.
+    fn on_restart(&mut self, _iteration: usize) -> Control {
+        Control::Continue
+    }
+
+    /// Called once the run is over, with all of its results.
+    fn on_termination(&mut self, _result_log: &ResultLog) {}
+}
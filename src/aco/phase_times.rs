@@ -0,0 +1,129 @@
+use serde_derive::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+const NUM_PHASES: usize = 5;
+
+/// The parts of a colony iteration whose running time can be measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    NewIteration,
+    ConstructSolutions,
+    UpdatePheromones,
+    /// Recalculation of the combined pheromone + heuristic matrix after a
+    /// pheromone update. Nested inside `UpdatePheromones`. Parallel colonies do
+    /// the whole pheromone update in the same pass, so it is all counted here.
+    RecomputeCombinedInfo,
+    /// Reinitialization of the trails in MMAS. Nested inside `NewIteration`.
+    Restart,
+}
+
+impl Phase {
+    pub const ALL: [Phase; NUM_PHASES] = [
+        Phase::NewIteration,
+        Phase::ConstructSolutions,
+        Phase::UpdatePheromones,
+        Phase::RecomputeCombinedInfo,
+        Phase::Restart,
+    ];
+}
+
+/// Accumulated time spent on each `Phase` over a run.
+///
+/// When disabled, `start` returns `None` and `record` does nothing, so the
+/// cost of leaving the calls in the colony loop is a single branch.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PhaseTimes {
+    enabled: bool,
+    totals: [Duration; NUM_PHASES],
+    calls: [u32; NUM_PHASES],
+}
+
+impl PhaseTimes {
+    pub fn new(enabled: bool) -> Self {
+        PhaseTimes {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Marks the start of a phase. Pass the returned value to `record` once the
+    /// phase is over.
+    pub fn start(&self) -> Option<Instant> {
+        if self.enabled {
+            Some(Instant::now())
+        } else {
+            None
+        }
+    }
+
+    pub fn record(&mut self, phase: Phase, start: Option<Instant>) {
+        if let Some(start) = start {
+            self.totals[phase as usize] += start.elapsed();
+            self.calls[phase as usize] += 1;
+        }
+    }
+
+    pub fn total(&self, phase: Phase) -> Duration {
+        self.totals[phase as usize]
+    }
+
+    pub fn calls(&self, phase: Phase) -> u32 {
+        self.calls[phase as usize]
+    }
+
+    /// Adds the times recorded in `other` to these.
+    pub fn merge(&mut self, other: &PhaseTimes) {
+        for i in 0..NUM_PHASES {
+            self.totals[i] += other.totals[i];
+            self.calls[i] += other.calls[i];
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merging_adds_each_phase() {
+        let mut times = PhaseTimes::new(true);
+        times.totals[Phase::ConstructSolutions as usize] = Duration::from_millis(30);
+        times.calls[Phase::ConstructSolutions as usize] = 3;
+        times.totals[Phase::UpdatePheromones as usize] = Duration::from_millis(5);
+        times.calls[Phase::UpdatePheromones as usize] = 3;
+        let mut other = PhaseTimes::new(true);
+        other.totals[Phase::ConstructSolutions as usize] = Duration::from_millis(12);
+        other.calls[Phase::ConstructSolutions as usize] = 2;
+        other.totals[Phase::Restart as usize] = Duration::from_millis(1);
+        other.calls[Phase::Restart as usize] = 1;
+
+        times.merge(&other);
+        assert_eq!(
+            times.total(Phase::ConstructSolutions),
+            Duration::from_millis(42)
+        );
+        assert_eq!(times.calls(Phase::ConstructSolutions), 5);
+        assert_eq!(
+            times.total(Phase::UpdatePheromones),
+            Duration::from_millis(5)
+        );
+        assert_eq!(times.calls(Phase::UpdatePheromones), 3);
+        assert_eq!(times.total(Phase::Restart), Duration::from_millis(1));
+        assert_eq!(times.calls(Phase::Restart), 1);
+        assert_eq!(times.calls(Phase::NewIteration), 0);
+    }
+
+    #[test]
+    fn disabled_times_record_nothing() {
+        let mut times = PhaseTimes::new(false);
+        let start = times.start();
+        assert!(start.is_none());
+        times.record(Phase::NewIteration, start);
+        assert_eq!(times.calls(Phase::NewIteration), 0);
+        assert_eq!(times.total(Phase::NewIteration), Duration::default());
+    }
+}
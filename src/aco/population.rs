@@ -0,0 +1,82 @@
+use super::Ant;
+
+/// All tours built by a colony in one iteration. Tours are stored back to back
+/// in a single allocation instead of one `Ant` each.
+#[derive(Debug, Clone)]
+pub struct Population {
+    num_cities: usize,
+    tours: Vec<u32>,
+    lengths: Vec<u32>,
+}
+
+impl Population {
+    pub fn with_capacity(num_ants: usize, num_cities: usize) -> Population {
+        Population {
+            num_cities,
+            tours: Vec::with_capacity(num_ants * num_cities),
+            lengths: Vec::with_capacity(num_ants),
+        }
+    }
+
+    pub fn from_ants(ants: &[Ant], num_cities: usize) -> Population {
+        let mut population = Self::with_capacity(ants.len(), num_cities);
+        for ant in ants {
+            population.push_ant(ant);
+        }
+        population
+    }
+
+    /// Adds a complete tour and its length. Panics if the tour doesn't visit
+    /// as many cities as the other tours.
+    pub fn push(&mut self, tour: &[u32], length: u32) {
+        assert_eq!(tour.len(), self.num_cities, "tour has the wrong size");
+        self.tours.extend_from_slice(tour);
+        self.lengths.push(length);
+    }
+
+    pub fn push_ant(&mut self, ant: &Ant) {
+        self.push(&ant.tour, ant.length);
+    }
+
+    pub fn len(&self) -> usize {
+        self.lengths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lengths.is_empty()
+    }
+
+    pub fn tour(&self, index: usize) -> &[u32] {
+        &self.tours[index * self.num_cities..(index + 1) * self.num_cities]
+    }
+
+    pub fn length(&self, index: usize) -> u32 {
+        self.lengths[index]
+    }
+
+    /// Iterates over (tour, length) pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u32], u32)> {
+        self.tours
+            .chunks(self.num_cities)
+            .zip(self.lengths.iter().cloned())
+    }
+
+    /// Index of the shortest tour. Panics if the population is empty.
+    pub fn best_index(&self) -> usize {
+        (0..self.len())
+            .min_by_key(|&i| self.lengths[i])
+            .expect("empty population")
+    }
+
+    /// Indices of the tours sorted from shortest to longest.
+    pub fn ranked(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..self.len()).collect();
+        indices.sort_by_key(|&i| self.lengths[i]);
+        indices
+    }
+
+    /// Creates an `Ant` with one of the tours.
+    pub fn ant(&self, index: usize) -> Ant {
+        Ant::completed(self.tour(index).to_vec(), self.lengths[index])
+    }
+}
@@ -0,0 +1,89 @@
+use super::acs::AcsColony;
+use super::acspar::AcsPar;
+use super::colony::Colony;
+use super::mmas::MmasColony;
+use super::AcoParameters;
+use crate::instance_data::InstanceData;
+use std::collections::HashMap;
+
+/// Function that sets up a colony for an instance with the given parameters.
+pub type ColonyConstructor = Box<
+    dyn for<'a> Fn(&'a InstanceData, &'a AcoParameters) -> Box<dyn Colony<'a> + 'a> + Send + Sync,
+>;
+
+/// Maps algorithm names to the constructors of their colonies, so colonies can be
+/// selected by name (e.g. from a run description) and new ones can be added
+/// without changing `run_aco`. Names are case insensitive.
+pub struct ColonyRegistry {
+    constructors: HashMap<String, ColonyConstructor>,
+}
+
+impl ColonyRegistry {
+    /// Creates a registry without any colonies.
+    pub fn empty() -> ColonyRegistry {
+        ColonyRegistry {
+            constructors: HashMap::new(),
+        }
+    }
+
+    /// Creates a registry with the MMAS and ACS colonies included in this crate.
+    pub fn with_builtin_colonies() -> ColonyRegistry {
+        let mut registry = Self::empty();
+        registry.register("Mmas", |data, parameters| {
+            Box::new(MmasColony::initialize_colony(data, parameters, false))
+        });
+        registry.register("MmasPar", |data, parameters| {
+            Box::new(MmasColony::initialize_colony(data, parameters, true))
+        });
+        registry.register("Acs", |data, parameters| {
+            Box::new(AcsColony::initialize_colony(data, parameters))
+        });
+        registry.register("AcsPar", |data, parameters| {
+            Box::new(AcsPar::initialize_colony(data, parameters))
+        });
+        registry
+    }
+
+    /// Adds a colony under the given name, replacing any colony previously
+    /// registered under it.
+    pub fn register<F>(&mut self, name: &str, constructor: F)
+    where
+        F: for<'a> Fn(&'a InstanceData, &'a AcoParameters) -> Box<dyn Colony<'a> + 'a>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.constructors
+            .insert(name.to_lowercase(), Box::new(constructor));
+    }
+
+    /// Names of all registered colonies, in lowercase.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.constructors.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Whether a colony is registered under `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.constructors.contains_key(&name.to_lowercase())
+    }
+
+    /// Sets up the colony registered under `name`, if there is one.
+    pub fn create<'a>(
+        &self,
+        name: &str,
+        data: &'a InstanceData,
+        parameters: &'a AcoParameters,
+    ) -> Option<Box<dyn Colony<'a> + 'a>> {
+        self.constructors
+            .get(&name.to_lowercase())
+            .map(|constructor| constructor(data, parameters))
+    }
+}
+
+impl Default for ColonyRegistry {
+    fn default() -> Self {
+        Self::with_builtin_colonies()
+    }
+}
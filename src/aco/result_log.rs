@@ -1,65 +1,91 @@
 use super::ant::Ant;
+use super::PhaseTimes;
+use crate::instance_data::InstanceData;
+use crate::util::{verify_tour, TourError};
+use serde_derive::{Deserialize, Serialize};
 use std::time::Duration;
 
-#[derive(Debug)]
+/// Length of the best tour of one iteration. Only the best tour of the whole run
+/// is kept, as keeping every iteration's would take far too much memory on large
+/// instances.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct TimestampedResult {
-    pub result: Ant,
+    pub length: u32,
     pub iteration: usize,
     pub timestamp: Duration,
     pub is_new_best: bool,
 }
 
-impl TimestampedResult {
-    fn new(result: Ant, iteration: usize, is_new_best: bool) -> Self {
-        TimestampedResult {
-            result,
-            iteration,
-            timestamp: crate::timer::elapsed(),
-            is_new_best,
-        }
-    }
-
-    fn length(&self) -> u32 {
-        self.result.length
-    }
-}
-
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ResultLog {
     pub log: Vec<TimestampedResult>,
     pub best_so_far: usize,
+    best: Option<Ant>,
+    /// Seed used by the colony's random number generators
+    pub seed: u64,
+    /// Time spent on each phase of the colony loop. Only filled if profiling
+    /// was enabled in the parameters.
+    pub phase_times: PhaseTimes,
+    /// Whether an observer stopped the run before it reached its limits, so that
+    /// its results are only partial
+    #[serde(default)]
+    pub stopped: bool,
 }
 
+/// Upper bound on how many entries are allocated up front, as the default
+/// iteration limit is far higher than what most runs reach.
+const MAX_INITIAL_CAPACITY: usize = 100_000;
+
 impl ResultLog {
     pub fn new(max_iters: usize) -> Self {
         ResultLog {
-            log: Vec::with_capacity(max_iters),
+            log: Vec::with_capacity(max_iters.min(MAX_INITIAL_CAPACITY)),
             best_so_far: 0,
+            best: None,
+            seed: 0,
+            phase_times: PhaseTimes::default(),
+            stopped: false,
         }
     }
 
-    pub fn latest_tour<'a>(&'a self) -> &'a Ant {
-        &self.log[self.log.len() - 1].result
+    pub fn latest(&self) -> &TimestampedResult {
+        &self.log[self.log.len() - 1]
     }
 
-    pub fn best_tour<'a>(&'a self) -> &'a Ant {
-        &self.log[self.best_so_far].result
+    pub fn best_tour(&self) -> &Ant {
+        self.best.as_ref().expect("no tour in the result log")
     }
 
-    pub fn best_timestamped<'a>(&'a self) -> &'a TimestampedResult {
+    pub fn best_timestamped(&self) -> &TimestampedResult {
         &self.log[self.best_so_far]
     }
 
     pub fn best_length(&self) -> u32 {
-        self.log[self.best_so_far].length()
+        self.log[self.best_so_far].length
     }
 
-    pub fn push(&mut self, new: Ant, iteration: usize) {
+    /// Checks that the best tour visits every city of the instance once, and
+    /// that its recorded length is the actual length of the tour.
+    pub fn verify(&self, data: &InstanceData) -> Result<(), TourError> {
+        if self.log.is_empty() {
+            return Ok(());
+        }
+        let best = self.best_tour();
+        let tour: Vec<usize> = best.cities().collect();
+        verify_tour(data, &tour, Some(best.length)).map(|_| ())
+    }
+
+    pub fn push(&mut self, new: Ant, iteration: usize, timestamp: Duration) {
         let is_new_best = self.log.is_empty() || new.length < self.best_length();
-        let timestamped = TimestampedResult::new(new, iteration, is_new_best);
-        self.log.push(timestamped);
+        self.log.push(TimestampedResult {
+            length: new.length,
+            iteration,
+            timestamp,
+            is_new_best,
+        });
         if is_new_best {
             self.best_so_far = self.log.len() - 1;
+            self.best = Some(new);
         }
     }
 }
@@ -0,0 +1,417 @@
+use crate::report::csv_field;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use parallelized_acotsp::result_file::{read_result_file, RunResult};
+use parallelized_acotsp::stats::{
+    friedman, holm, mann_whitney_u, median, rank_difference_p_value, wilcoxon_signed_rank,
+};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("compare")
+        .about("Tests whether the results in two or more result directories differ")
+        .arg(
+            Arg::with_name("RESULT DIRECTORY")
+                .help("Directories of result files, such as the out_path of the runs of each algorithm. Result files are matched across directories by the subdirectory they are in, so results/mmas/rat783 is compared with results/mmas_par/rat783")
+                .required(true)
+                .multiple(true)
+                .min_values(2),
+        )
+        .arg(
+            Arg::with_name("Csv")
+                .long("csv")
+                .takes_value(true)
+                .help("CSV file the tests are also written to"),
+        )
+}
+
+/// Result files of a directory, by the subdirectory they are in.
+struct ResultSet {
+    name: String,
+    instances: BTreeMap<String, Vec<RunResult>>,
+}
+
+/// A value of each run that the result sets are compared on.
+#[derive(Clone, Copy)]
+enum Measure {
+    Length,
+    TimeToBest,
+}
+
+impl Measure {
+    const ALL: [Measure; 2] = [Measure::Length, Measure::TimeToBest];
+
+    fn name(self) -> &'static str {
+        match self {
+            Measure::Length => "length",
+            Measure::TimeToBest => "time to best",
+        }
+    }
+
+    fn value(self, run: &RunResult) -> f64 {
+        match self {
+            Measure::Length => run.best_length as f64,
+            Measure::TimeToBest => run.best_timestamp.as_secs_f64(),
+        }
+    }
+
+    fn values(self, runs: &[RunResult]) -> Vec<f64> {
+        runs.iter().map(|r| self.value(r)).collect()
+    }
+}
+
+/// One row of the table of tests.
+struct Comparison {
+    instance: String,
+    measure: &'static str,
+    test: &'static str,
+    sets: String,
+    statistic: f64,
+    p_value: f64,
+    effect: Option<(&'static str, f64)>,
+}
+
+pub fn run(matches: &ArgMatches) {
+    let sets: Vec<ResultSet> = matches
+        .values_of("RESULT DIRECTORY")
+        .expect("failed parsing argument")
+        .map(read_result_set)
+        .collect();
+
+    let mut summary = Vec::new();
+    for set in sets.iter() {
+        for (instance, runs) in set.instances.iter() {
+            summary.push(vec![
+                instance.clone(),
+                set.name.clone(),
+                runs.len().to_string(),
+                format!("{:.1}", median(&Measure::Length.values(runs))),
+                format!("{:.3}", median(&Measure::TimeToBest.values(runs))),
+            ]);
+        }
+    }
+    summary.sort();
+    print_table(
+        &[
+            "instance",
+            "results",
+            "runs",
+            "median length",
+            "median time to best (s)",
+        ],
+        &summary,
+    );
+    println!();
+
+    let comparisons: Vec<Comparison> = Measure::ALL
+        .iter()
+        .flat_map(|&measure| compare(&sets, measure))
+        .collect();
+    let rows: Vec<Vec<String>> = comparisons
+        .iter()
+        .map(|c| {
+            vec![
+                c.instance.clone(),
+                c.measure.to_owned(),
+                c.test.to_owned(),
+                c.sets.clone(),
+                format!("{:.3}", c.statistic),
+                format_p_value(c.p_value),
+                c.effect
+                    .map_or("-".to_owned(), |(name, e)| format!("{} = {:.3}", name, e)),
+            ]
+        })
+        .collect();
+    print_table(
+        &[
+            "instance",
+            "measure",
+            "test",
+            "results",
+            "statistic",
+            "p",
+            "effect size",
+        ],
+        &rows,
+    );
+    if let Some(csv_file) = matches.value_of("Csv") {
+        write_csv(csv_file, &comparisons).expect("failed writing comparison");
+    }
+}
+
+fn read_result_set(dir: &str) -> ResultSet {
+    let mut set = ResultSet {
+        name: dir.to_owned(),
+        instances: BTreeMap::new(),
+    };
+    read_result_dir(Path::new(dir), Path::new(dir), &mut set);
+    if set.instances.is_empty() {
+        panic!("no result files found in {}", dir);
+    }
+    set
+}
+
+fn read_result_dir(root: &Path, dir: &Path, set: &mut ResultSet) {
+    let entries = fs::read_dir(dir).expect("failed reading result directory");
+    let mut paths: Vec<_> = entries
+        .map(|e| e.expect("failed reading result directory").path())
+        .collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            read_result_dir(root, &path, set);
+        } else if path.extension().is_some_and(|e| e == "txt") {
+            match read_result_file(&path) {
+                Ok(result) => {
+                    let instance = dir.strip_prefix(root).unwrap_or(dir).display().to_string();
+                    let instance = if instance.is_empty() {
+                        ".".to_owned()
+                    } else {
+                        instance
+                    };
+                    set.instances.entry(instance).or_default().push(result);
+                }
+                Err(e) => eprintln!("skipping {}", e),
+            }
+        }
+    }
+}
+
+/// Tests of one measure: Mann-Whitney U between every two result sets on each
+/// instance, Wilcoxon signed-rank on the runs of an instance that used the same
+/// seeds, and over the instances they share, Wilcoxon signed-rank on the
+/// relative medians of each instance. With more than two result sets, also a
+/// Friedman test on the instances all of them have, followed by comparisons of
+/// every two with Holm's correction.
+fn compare(sets: &[ResultSet], measure: Measure) -> Vec<Comparison> {
+    let mut comparisons = Vec::new();
+    let pairs: Vec<(usize, usize)> = (0..sets.len())
+        .flat_map(|a| (a + 1..sets.len()).map(move |b| (a, b)))
+        .collect();
+    let pair_name = |a: usize, b: usize| format!("{} vs {}", sets[a].name, sets[b].name);
+
+    for &(a, b) in pairs.iter() {
+        for (instance, runs_a) in sets[a].instances.iter() {
+            if let Some(runs_b) = sets[b].instances.get(instance) {
+                let result = mann_whitney_u(&measure.values(runs_a), &measure.values(runs_b));
+                comparisons.push(Comparison {
+                    instance: instance.clone(),
+                    measure: measure.name(),
+                    test: "Mann-Whitney U",
+                    sets: pair_name(a, b),
+                    statistic: result.statistic,
+                    p_value: result.p_value,
+                    effect: Some(("A12", result.effect_size)),
+                });
+                let (paired_a, paired_b) = paired_by_seed(runs_a, runs_b, measure);
+                if paired_a.len() >= 2 {
+                    let result = wilcoxon_signed_rank(&paired_a, &paired_b);
+                    comparisons.push(Comparison {
+                        instance: instance.clone(),
+                        measure: measure.name(),
+                        test: "Wilcoxon signed-rank (same seeds)",
+                        sets: pair_name(a, b),
+                        statistic: result.statistic,
+                        p_value: result.p_value,
+                        effect: Some(("r", result.effect_size)),
+                    });
+                }
+            }
+        }
+    }
+
+    for &(a, b) in pairs.iter() {
+        let (medians_a, medians_b): (Vec<f64>, Vec<f64>) = sets[a]
+            .instances
+            .iter()
+            .filter_map(|(instance, runs_a)| {
+                let runs_b = sets[b].instances.get(instance)?;
+                Some(relative(
+                    median(&measure.values(runs_a)),
+                    median(&measure.values(runs_b)),
+                ))
+            })
+            .unzip();
+        if medians_a.len() < 2 {
+            continue;
+        }
+        let result = wilcoxon_signed_rank(&medians_a, &medians_b);
+        comparisons.push(Comparison {
+            instance: format!("{} instances", medians_a.len()),
+            measure: measure.name(),
+            test: "Wilcoxon signed-rank",
+            sets: pair_name(a, b),
+            statistic: result.statistic,
+            p_value: result.p_value,
+            effect: Some(("r", result.effect_size)),
+        });
+    }
+
+    if sets.len() > 2 {
+        let blocks: Vec<Vec<f64>> = sets[0]
+            .instances
+            .keys()
+            .filter_map(|instance| {
+                sets.iter()
+                    .map(|set| Some(median(&measure.values(set.instances.get(instance)?))))
+                    .collect()
+            })
+            .collect();
+        if blocks.len() >= 2 {
+            let instances = format!("{} instances", blocks.len());
+            let (result, mean_ranks) = friedman(&blocks);
+            comparisons.push(Comparison {
+                instance: instances.clone(),
+                measure: measure.name(),
+                test: "Friedman",
+                sets: "all".to_owned(),
+                statistic: result.statistic,
+                p_value: result.p_value,
+                effect: Some(("W", result.effect_size)),
+            });
+            let p_values: Vec<f64> = pairs
+                .iter()
+                .map(|&(a, b)| {
+                    rank_difference_p_value(mean_ranks[a], mean_ranks[b], sets.len(), blocks.len())
+                })
+                .collect();
+            for (&(a, b), p_value) in pairs.iter().zip(holm(&p_values)) {
+                comparisons.push(Comparison {
+                    instance: instances.clone(),
+                    measure: measure.name(),
+                    test: "Friedman post-hoc (Holm)",
+                    sets: pair_name(a, b),
+                    statistic: mean_ranks[a] - mean_ranks[b],
+                    p_value,
+                    effect: None,
+                });
+            }
+        }
+    }
+    comparisons
+}
+
+/// The values of an instance divided by their mean, so that the differences of
+/// instances on very different scales can be ranked together.
+fn relative(a: f64, b: f64) -> (f64, f64) {
+    let mean = (a + b) / 2.0;
+    if mean == 0.0 {
+        (a, b)
+    } else {
+        (a / mean, b / mean)
+    }
+}
+
+/// Values of the runs of `runs_a` and `runs_b` that used the same seed, in
+/// matching order. Runs with no counterpart are left out.
+fn paired_by_seed(
+    runs_a: &[RunResult],
+    runs_b: &[RunResult],
+    measure: Measure,
+) -> (Vec<f64>, Vec<f64>) {
+    let mut by_seed: BTreeMap<u64, Vec<&RunResult>> = BTreeMap::new();
+    for run in runs_b.iter().rev() {
+        by_seed.entry(run.seed).or_default().push(run);
+    }
+    runs_a
+        .iter()
+        .filter_map(|run_a| {
+            let run_b = by_seed.get_mut(&run_a.seed)?.pop()?;
+            Some((measure.value(run_a), measure.value(run_b)))
+        })
+        .unzip()
+}
+
+fn format_p_value(p: f64) -> String {
+    if p < 1e-4 {
+        format!("{:.2e}", p)
+    } else {
+        format!("{:.4}", p)
+    }
+}
+
+/// Prints rows with their columns aligned under the header.
+fn print_table(header: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, &width)| format!("{:width$}", cell, width = width))
+            .collect();
+        println!("{}", padded.join("  ").trim_end());
+    };
+    line(header.to_vec());
+    for row in rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
+
+fn write_csv(file_name: &str, comparisons: &[Comparison]) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(file_name)?);
+    writeln!(
+        out,
+        "instance,measure,test,results,statistic,p_value,effect_size_name,effect_size"
+    )?;
+    for c in comparisons {
+        let (effect_name, effect) = c
+            .effect
+            .map_or((String::new(), String::new()), |(name, e)| {
+                (name.to_owned(), e.to_string())
+            });
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{}",
+            csv_field(&c.instance),
+            csv_field(c.measure),
+            csv_field(c.test),
+            csv_field(&c.sets),
+            c.statistic,
+            c.p_value,
+            effect_name,
+            effect
+        )?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    fn run(seed: u64, best_length: u32) -> RunResult {
+        RunResult {
+            best_length,
+            optimum: None,
+            seed,
+            best_tour: Vec::new(),
+            best_iteration: 0,
+            best_timestamp: Duration::from_secs(0),
+            iterations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn runs_are_paired_by_seed() {
+        let runs_a = [run(1, 10), run(2, 20), run(3, 30), run(1, 11)];
+        let runs_b = [run(3, 31), run(1, 12), run(4, 40), run(1, 13)];
+        let (a, b) = paired_by_seed(&runs_a, &runs_b, Measure::Length);
+        assert_eq!(a, vec![10.0, 30.0, 11.0]);
+        assert_eq!(b, vec![12.0, 31.0, 13.0]);
+    }
+
+    #[test]
+    fn medians_are_compared_relative_to_their_instance() {
+        assert_eq!(relative(90.0, 110.0), (0.9, 1.1));
+        assert_eq!(relative(900_000.0, 1_100_000.0), (0.9, 1.1));
+        assert_eq!(relative(0.0, 0.0), (0.0, 0.0));
+    }
+}
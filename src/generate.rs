@@ -0,0 +1,119 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use parallelized_acotsp::generator;
+use parallelized_acotsp::tsplibwriter::{write_coordinates_instance, write_matrix_instance};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("generate")
+        .about("Writes a random instance as a TSPLIB file")
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("TSPLIB file to write")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("Kind")
+                .long("kind")
+                .takes_value(true)
+                .possible_values(&["uniform", "clustered", "grid", "metric", "asymmetric"])
+                .help("uniform: cities spread uniformly over a square. clustered: cities around size/10 centres, as in DIMACS portcgen. grid: a square grid with jitter. metric and asymmetric: random distance matrices that satisfy the triangle inequality (default uniform)"),
+        )
+        .arg(
+            Arg::with_name("Size")
+                .short("n")
+                .long("size")
+                .takes_value(true)
+                .required(true)
+                .help("Number of cities"),
+        )
+        .arg(
+            Arg::with_name("Seed")
+                .long("seed")
+                .takes_value(true)
+                .help("Seed of the random numbers, for generating the same instance again (default: random, and written in the file's comment)"),
+        )
+        .arg(
+            Arg::with_name("Side")
+                .long("side")
+                .takes_value(true)
+                .help("Side of the square cities are placed in (default 1000000)"),
+        )
+        .arg(
+            Arg::with_name("Jitter")
+                .long("jitter")
+                .takes_value(true)
+                .help("Largest distance grid cities are moved by, as a fraction of the grid spacing (default 0.25)"),
+        )
+        .arg(
+            Arg::with_name("Max Distance")
+                .long("max-distance")
+                .takes_value(true)
+                .help("Largest distance drawn for distance matrices, before shortest paths replace longer distances (default 1000)"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) {
+    let output = Path::new(matches.value_of("OUTPUT").expect("failed parsing argument"));
+    let kind = matches.value_of("Kind").unwrap_or("uniform");
+    let size: usize = matches
+        .value_of("Size")
+        .expect("failed parsing argument")
+        .parse()
+        .expect("invalid number of cities");
+    let seed: u64 = matches.value_of("Seed").map_or_else(
+        || rand::thread_rng().gen(),
+        |seed| seed.parse().expect("invalid seed"),
+    );
+    let side: f64 = matches
+        .value_of("Side")
+        .unwrap_or("1000000")
+        .parse()
+        .expect("invalid side");
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let name = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let comment = format!(
+        "{} instance of {} cities generated with seed {}",
+        kind, size, seed
+    );
+    let mut out = BufWriter::new(File::create(output).expect("failed creating instance file"));
+    match kind {
+        "uniform" | "clustered" | "grid" => {
+            let coordinates = match kind {
+                "uniform" => generator::uniform(size, side, &mut rng),
+                "clustered" => generator::clustered(size, side, &mut rng),
+                _ => {
+                    let jitter: f64 = matches
+                        .value_of("Jitter")
+                        .unwrap_or("0.25")
+                        .parse()
+                        .expect("invalid jitter");
+                    generator::grid(size, side, jitter, &mut rng)
+                }
+            };
+            write_coordinates_instance(&mut out, &name, &comment, &coordinates)
+        }
+        _ => {
+            let max_distance: u32 = matches
+                .value_of("Max Distance")
+                .unwrap_or("1000")
+                .parse()
+                .expect("invalid maximum distance");
+            assert!(max_distance > 0, "maximum distance must be positive");
+            let symmetric = kind == "metric";
+            let distances =
+                generator::random_metric_matrix(size, max_distance, symmetric, &mut rng);
+            write_matrix_instance(&mut out, &name, &comment, &distances)
+        }
+    }
+    .and_then(|_| out.flush())
+    .expect("failed writing instance");
+    println!("wrote {} ({})", output.display(), comment);
+}
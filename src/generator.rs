@@ -0,0 +1,169 @@
+//! Random instances for testing how the colonies scale, in the style of the
+//! generators of the DIMACS TSP challenge.
+
+use rand::Rng;
+
+/// Cities at integer coordinates drawn uniformly from a square of the given
+/// side, as in DIMACS portgen.
+pub fn uniform(size: usize, side: f64, rng: &mut impl Rng) -> Vec<(f64, f64)> {
+    (0..size)
+        .map(|_| {
+            let x = rng.gen_range(0.0, side);
+            let y = rng.gen_range(0.0, side);
+            (x.floor(), y.floor())
+        })
+        .collect()
+}
+
+/// Cities around `size / 10` centres drawn uniformly from a square of the given
+/// side, as in DIMACS portcgen. Each city picks a centre at random and is placed
+/// around it with a normal distribution, of standard deviation `side / sqrt(size)`
+/// in each coordinate.
+pub fn clustered(size: usize, side: f64, rng: &mut impl Rng) -> Vec<(f64, f64)> {
+    let centres = uniform((size / 10).max(1), side, rng);
+    let deviation = side / (size as f64).sqrt();
+    (0..size)
+        .map(|_| {
+            let (cx, cy) = centres[rng.gen_range(0, centres.len())];
+            let (dx, dy) = standard_normal_pair(rng);
+            ((cx + dx * deviation).round(), (cy + dy * deviation).round())
+        })
+        .collect()
+}
+
+/// Two independent samples of the standard normal distribution, by the
+/// Box-Muller transform.
+fn standard_normal_pair(rng: &mut impl Rng) -> (f64, f64) {
+    // 1 - u is in (0, 1], so its logarithm is finite
+    let u: f64 = 1.0 - rng.gen::<f64>();
+    let v: f64 = rng.gen();
+    let radius = (-2.0 * u.ln()).sqrt();
+    let angle = 2.0 * std::f64::consts::PI * v;
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+/// Cities on the points of a square grid over a square of the given side, each
+/// moved in both coordinates by up to `jitter` times the grid spacing. The last
+/// row is left incomplete if `size` is not a square number.
+pub fn grid(size: usize, side: f64, jitter: f64, rng: &mut impl Rng) -> Vec<(f64, f64)> {
+    let columns = (size as f64).sqrt().ceil().max(1.0) as usize;
+    let spacing = side / columns as f64;
+    let mut offset = || {
+        if jitter > 0.0 {
+            rng.gen_range(-jitter, jitter) * spacing
+        } else {
+            0.0
+        }
+    };
+    (0..size)
+        .map(|i| {
+            let x = ((i % columns) as f64 + 0.5) * spacing + offset();
+            let y = ((i / columns) as f64 + 0.5) * spacing + offset();
+            (x.round(), y.round())
+        })
+        .collect()
+}
+
+/// Distances between every two cities that satisfy the triangle inequality.
+/// Distances are first drawn uniformly from 1 to `max_distance`, and then
+/// replaced by the shortest path between the cities. If `symmetric`, the distance
+/// from i to j is the same as from j to i. `max_distance` may be anything from 1
+/// up to `u32::MAX`.
+///
+/// Finding the shortest paths takes time cubic in `size`.
+pub fn random_metric_matrix(
+    size: usize,
+    max_distance: u32,
+    symmetric: bool,
+    rng: &mut impl Rng,
+) -> Vec<Vec<u32>> {
+    assert!(max_distance > 0, "the largest distance must be positive");
+    let mut distances: Vec<Vec<u32>> = (0..size)
+        .map(|i| {
+            (0..size)
+                .map(|j| {
+                    if i == j {
+                        0
+                    } else {
+                        rng.gen_range(1, u64::from(max_distance) + 1) as u32
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    if symmetric {
+        // copy the upper triangle over the lower one
+        for i in 1..size {
+            let (upper, lower) = distances.split_at_mut(i);
+            for (j, row) in upper.iter().enumerate() {
+                lower[0][j] = row[i];
+            }
+        }
+    }
+    // Floyd-Warshall
+    for k in 0..size {
+        let through = distances[k].clone();
+        for row in distances.iter_mut() {
+            let to_k = row[k];
+            for (distance, &from_k) in row.iter_mut().zip(through.iter()) {
+                // a path longer than u32::MAX is never the shortest one
+                *distance = (*distance).min(to_k.saturating_add(from_k));
+            }
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn generated_instances_have_expected_shape() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let in_square =
+            |&(x, y): &(f64, f64)| (0.0..1000.0).contains(&x) && (0.0..1000.0).contains(&y);
+        assert!(uniform(500, 1000.0, &mut rng).iter().all(in_square));
+        assert_eq!(clustered(500, 1000.0, &mut rng).len(), 500);
+        let points = grid(10, 1000.0, 0.0, &mut rng);
+        assert_eq!(
+            points[..5],
+            [
+                (125.0, 125.0),
+                (375.0, 125.0),
+                (625.0, 125.0),
+                (875.0, 125.0),
+                (125.0, 375.0)
+            ]
+        );
+        assert!(grid(100, 1000.0, 0.4, &mut rng).iter().all(in_square));
+
+        for &symmetric in [true, false].iter() {
+            let distances = random_metric_matrix(30, 1000, symmetric, &mut rng);
+            for i in 0..30 {
+                assert_eq!(distances[i][i], 0);
+                for j in 0..30 {
+                    if symmetric {
+                        assert_eq!(distances[i][j], distances[j][i]);
+                    }
+                    for k in 0..30 {
+                        assert!(distances[i][j] <= distances[i][k] + distances[k][j]);
+                    }
+                }
+            }
+        }
+
+        // distances up to the largest u32 don't overflow
+        let distances = random_metric_matrix(10, u32::MAX, false, &mut rng);
+        for i in 0..10 {
+            for j in 0..10 {
+                for k in 0..10 {
+                    let through_k = u64::from(distances[i][k]) + u64::from(distances[k][j]);
+                    assert!(u64::from(distances[i][j]) <= through_k);
+                }
+            }
+        }
+    }
+}
@@ -1,25 +1,327 @@
-use crate::util::IntegerMatrix;
+pub use crate::util::CandidateLists;
+use crate::util::{distance_funcs, IntegerMatrix, SymmetricMatrix};
+use rayon::prelude::*;
 use std::str::FromStr;
 
+/// An instance read from a file, with everything else the file described.
 pub struct FileData {
     pub metadata: Metadata,
     pub data: InstanceData,
+    /// Coordinates of each city, if the file gave them
+    pub coordinates: Option<Coordinates>,
+    /// Coordinates of each city meant only for drawing the instance, for files
+    /// that give distances some other way (DISPLAY_DATA_SECTION)
+    pub display_data: Option<Vec<(f64, f64)>>,
+}
+
+impl FileData {
+    /// Coordinates to draw each city at: the display data if there is any, or
+    /// else the coordinates, leaving out the third one of 3D coordinates and
+    /// drawing longitudes across and latitudes up.
+    pub fn display_coordinates(&self) -> Option<Vec<(f64, f64)>> {
+        match (&self.display_data, &self.coordinates) {
+            (Some(display_data), _) => Some(display_data.clone()),
+            (None, Some(Coordinates::TwoD(coordinates))) => Some(coordinates.clone()),
+            (None, Some(Coordinates::ThreeD(coordinates))) => {
+                Some(coordinates.iter().map(|&(x, y, _)| (x, y)).collect())
+            }
+            (None, Some(Coordinates::Geographic(coordinates))) => {
+                Some(coordinates.iter().map(|&(lat, lon)| (lon, lat)).collect())
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+/// Coordinates of every city of an instance.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Coordinates {
+    TwoD(Vec<(f64, f64)>),
+    ThreeD(Vec<(f64, f64, f64)>),
+    /// (latitude, longitude) in decimal degrees
+    Geographic(Vec<(f64, f64)>),
+}
+
+impl Coordinates {
+    pub fn len(&self) -> usize {
+        match self {
+            Coordinates::TwoD(coordinates) => coordinates.len(),
+            Coordinates::ThreeD(coordinates) => coordinates.len(),
+            Coordinates::Geographic(coordinates) => coordinates.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 pub struct InstanceData {
     pub size: usize,
-    pub distances: IntegerMatrix,
+    distances: Distances,
 }
 
-#[allow(non_camel_case_types)]
-pub enum DataDescriptionType {
-    NODE_COORD_SECTION,
-    // Other variants left out until implemented
+/// How the distance between two cities is obtained.
+enum Distances {
+    /// Stored for every pair of cities
+    Matrix(IntegerMatrix),
+    /// Stored once for every pair of cities, when the distance from i to j is
+    /// the same as from j to i
+    Symmetric(SymmetricMatrix<u32>),
+    /// Calculated from the coordinates whenever needed, for instances too large
+    /// to store every distance
+    Euc2d(Vec<(f64, f64)>),
+    /// Calculated from 3D coordinates whenever needed, for the same reason
+    Euc3d(Vec<(f64, f64, f64)>),
+    /// Great-circle distances calculated from (latitude, longitude) whenever
+    /// needed, for the same reason
+    Geographic(Vec<(f64, f64)>),
+}
+
+/// Instances with more cities than this calculate distances from their
+/// coordinates on demand instead of storing a full distance matrix.
+pub const MAX_MATRIX_SIZE: usize = 10_000;
+
+impl InstanceData {
+    /// Creates an instance from a full matrix of distances, where `distances[i][j]` is
+    /// the distance from city `i` to city `j`. The diagonal is ignored. Symmetric
+    /// matrices only store each distance once.
+    ///
+    /// Panics if the matrix is not square.
+    pub fn from_distance_matrix(distances: &[Vec<u32>]) -> InstanceData {
+        let size = distances.len();
+        for row in distances {
+            assert_eq!(row.len(), size, "distance matrix is not square");
+        }
+        let symmetric = (0..size).all(|i| (0..i).all(|j| distances[i][j] == distances[j][i]));
+        let distances = if symmetric {
+            let mut matrix = SymmetricMatrix::with_element(size, u32::MAX);
+            for i in 0..size {
+                matrix.lower_row_mut(i).copy_from_slice(&distances[i][..i]);
+            }
+            Distances::Symmetric(matrix)
+        } else {
+            let mut matrix = IntegerMatrix::with_capacity(size);
+            for (i, row) in distances.iter().enumerate() {
+                for (j, &d) in row.iter().enumerate() {
+                    matrix.push(if i == j { u32::MAX } else { d });
+                }
+            }
+            Distances::Matrix(matrix)
+        };
+        InstanceData { size, distances }
+    }
+
+    /// Creates an instance from the coordinates of each city, using rounded
+    /// euclidean distances (EUC_2D in TSPLIB). Distances are only precomputed
+    /// for instances of up to `MAX_MATRIX_SIZE` cities.
+    pub fn from_coordinates(coordinates: &[(f64, f64)]) -> InstanceData {
+        Self::euc_2d(coordinates, coordinates.len() <= MAX_MATRIX_SIZE)
+    }
+
+    /// Creates an instance from 3D coordinates, using rounded euclidean distances
+    /// (EUC_3D in TSPLIB). Distances are only precomputed for instances of up to
+    /// `MAX_MATRIX_SIZE` cities.
+    pub fn from_3d_coordinates(coordinates: &[(f64, f64, f64)]) -> InstanceData {
+        let size = coordinates.len();
+        if size > MAX_MATRIX_SIZE {
+            return InstanceData {
+                size,
+                distances: Distances::Euc3d(coordinates.to_vec()),
+            };
+        }
+        let mut distances = SymmetricMatrix::with_element(size, u32::MAX);
+        for (i, &node_i) in coordinates.iter().enumerate() {
+            for (j, distance) in distances.lower_row_mut(i).iter_mut().enumerate() {
+                *distance = distance_funcs::euc_3d(node_i, coordinates[j]);
+            }
+        }
+        InstanceData {
+            size,
+            distances: Distances::Symmetric(distances),
+        }
+    }
+
+    /// Creates an instance from the (latitude, longitude) of each city in decimal
+    /// degrees, using great-circle distances in kilometres. Distances are only
+    /// precomputed for instances of up to `MAX_MATRIX_SIZE` cities.
+    ///
+    /// Panics if a latitude is outside ±90 or a longitude outside ±180 degrees.
+    pub fn from_geographic_coordinates(coordinates: &[(f64, f64)]) -> InstanceData {
+        for &(lat, lon) in coordinates {
+            assert!(
+                (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon),
+                "({}, {}) is not a latitude and longitude in degrees",
+                lat,
+                lon
+            );
+        }
+        let size = coordinates.len();
+        if size > MAX_MATRIX_SIZE {
+            return InstanceData {
+                size,
+                distances: Distances::Geographic(coordinates.to_vec()),
+            };
+        }
+        let mut distances = SymmetricMatrix::with_element(size, u32::MAX);
+        for (i, &node_i) in coordinates.iter().enumerate() {
+            for (j, distance) in distances.lower_row_mut(i).iter_mut().enumerate() {
+                *distance = distance_funcs::haversine(node_i, coordinates[j]);
+            }
+        }
+        InstanceData {
+            size,
+            distances: Distances::Symmetric(distances),
+        }
+    }
+
+    fn euc_2d(coordinates: &[(f64, f64)], precompute: bool) -> InstanceData {
+        let size = coordinates.len();
+        if !precompute {
+            return InstanceData {
+                size,
+                distances: Distances::Euc2d(coordinates.to_vec()),
+            };
+        }
+        let mut distances = SymmetricMatrix::with_element(size, u32::MAX);
+        for (i, &node_i) in coordinates.iter().enumerate() {
+            for (j, distance) in distances.lower_row_mut(i).iter_mut().enumerate() {
+                *distance = distance_funcs::euc_2d(node_i, coordinates[j]);
+            }
+        }
+        InstanceData {
+            size,
+            distances: Distances::Symmetric(distances),
+        }
+    }
+
+    /// Whether the distance from i to j is always the same as from j to i.
+    pub fn is_symmetric(&self) -> bool {
+        match self.distances {
+            Distances::Matrix(_) => false,
+            Distances::Symmetric(_)
+            | Distances::Euc2d(_)
+            | Distances::Euc3d(_)
+            | Distances::Geographic(_) => true,
+        }
+    }
+
+    /// Distance from city `i` to city `j`. The distance from a city to itself is
+    /// `u32::MAX`.
+    #[inline]
+    pub fn distance(&self, i: usize, j: usize) -> u32 {
+        match &self.distances {
+            // `Matrix` is indexed by (column, row)
+            Distances::Matrix(matrix) => matrix[(j, i)],
+            Distances::Symmetric(matrix) => matrix[(i, j)],
+            Distances::Euc2d(_) if i == j => u32::MAX,
+            Distances::Euc2d(coordinates) => distance_funcs::euc_2d(coordinates[i], coordinates[j]),
+            Distances::Euc3d(_) if i == j => u32::MAX,
+            Distances::Euc3d(coordinates) => distance_funcs::euc_3d(coordinates[i], coordinates[j]),
+            Distances::Geographic(_) if i == j => u32::MAX,
+            Distances::Geographic(coordinates) => {
+                distance_funcs::haversine(coordinates[i], coordinates[j])
+            }
+        }
+    }
+
+    /// Finds the `k` nearest cities to every city, closest first. `k` is limited
+    /// to the number of other cities.
+    pub fn nearest_neighbours(&self, k: usize) -> CandidateLists {
+        let k = k.min(self.size.saturating_sub(1));
+        let mut lists = vec![0; self.size * k];
+        if k == 0 {
+            return CandidateLists::new(lists, k);
+        }
+        match &self.distances {
+            Distances::Euc2d(coordinates) => grid_nearest_neighbours(coordinates, k, &mut lists),
+            _ => {
+                lists.par_chunks_mut(k).enumerate().for_each(|(i, list)| {
+                    let mut cities: Vec<u32> =
+                        (0..self.size as u32).filter(|&j| j as usize != i).collect();
+                    let distance = |&j: &u32| self.distance(i, j as usize);
+                    cities.select_nth_unstable_by_key(k - 1, distance);
+                    cities.truncate(k);
+                    cities.sort_by_key(distance);
+                    list.copy_from_slice(&cities);
+                });
+            }
+        }
+        CandidateLists::new(lists, k)
+    }
+}
+
+/// Finds the `k` nearest neighbours of every point, writing them to `lists`.
+/// Points are bucketed in a grid so only the cells around each point have to be
+/// searched, which keeps this close to linear for large instances.
+fn grid_nearest_neighbours(points: &[(f64, f64)], k: usize, lists: &mut [u32]) {
+    let (min_x, max_x, min_y, max_y) = points.iter().fold(
+        (
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |(min_x, max_x, min_y, max_y), &(x, y)| {
+            (min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y))
+        },
+    );
+    // around two points per cell
+    let cells = ((points.len() as f64 / 2.0).sqrt().ceil() as usize).max(1);
+    let cell_size = ((max_x - min_x).max(max_y - min_y) / cells as f64).max(f64::MIN_POSITIVE);
+    let cell_of = |(x, y): (f64, f64)| {
+        let cx = (((x - min_x) / cell_size) as usize).min(cells - 1);
+        let cy = (((y - min_y) / cell_size) as usize).min(cells - 1);
+        (cx, cy)
+    };
+    let mut grid = vec![Vec::new(); cells * cells];
+    for (i, &point) in points.iter().enumerate() {
+        let (cx, cy) = cell_of(point);
+        grid[cy * cells + cx].push(i);
+    }
+    let squared_distance =
+        |(x1, y1): (f64, f64), (x2, y2): (f64, f64)| (x1 - x2).powi(2) + (y1 - y2).powi(2);
+    let by_distance =
+        |a: &(f64, usize), b: &(f64, usize)| a.partial_cmp(b).expect("failed comparison");
+    lists.par_chunks_mut(k).enumerate().for_each(|(i, list)| {
+        let (cx, cy) = cell_of(points[i]);
+        let mut found: Vec<(f64, usize)> = Vec::new();
+        for ring in 0..cells {
+            for gy in cy.saturating_sub(ring)..=(cy + ring).min(cells - 1) {
+                for gx in cx.saturating_sub(ring)..=(cx + ring).min(cells - 1) {
+                    // only the cells on the border of the ring, the rest were already searched
+                    if gx.max(cx) - gx.min(cx) != ring && gy.max(cy) - gy.min(cy) != ring {
+                        continue;
+                    }
+                    for &j in grid[gy * cells + gx].iter().filter(|&&j| j != i) {
+                        found.push((squared_distance(points[i], points[j]), j));
+                    }
+                }
+            }
+            if found.len() >= k {
+                found.sort_unstable_by(by_distance);
+                found.truncate(k);
+                // points in cells outside this ring are at least this far away
+                let searched = ring as f64 * cell_size;
+                if found[k - 1].0 <= searched * searched {
+                    break;
+                }
+            }
+        }
+        found.sort_unstable_by(by_distance);
+        for (city, &(_, j)) in list.iter_mut().zip(found.iter()) {
+            *city = j as u32;
+        }
+    });
 }
 
 #[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EdgeWeightType {
     EUC_2D,
+    EUC_3D,
+    /// Distances given in an EDGE_WEIGHT_SECTION
+    EXPLICIT,
     // Other variants left out until implemented
 }
 impl FromStr for EdgeWeightType {
@@ -27,13 +329,93 @@ impl FromStr for EdgeWeightType {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "EUC_2D" => Ok(EdgeWeightType::EUC_2D),
-            _ => unimplemented!(),
+            "EUC_3D" => Ok(EdgeWeightType::EUC_3D),
+            "EXPLICIT" => Ok(EdgeWeightType::EXPLICIT),
+            _ => Err(format!("unsupported EDGE_WEIGHT_TYPE {}", s)),
         }
     }
 }
 
-#[derive(Default)]
+/// The specification part of a TSPLIB file. Fields the file leaves out are `None`.
+#[derive(Debug, Default)]
 pub struct Metadata {
     pub name: String,
+    /// TYPE of the data, such as TSP or ATSP
+    pub problem_type: Option<String>,
+    /// Every COMMENT line, separated by newlines
+    pub comment: Option<String>,
+    /// Number of cities
+    pub dimension: Option<usize>,
     pub edge_weight_type: Option<EdgeWeightType>,
+    pub edge_weight_format: Option<String>,
+    pub display_data_type: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn distance_matrix_symmetry_is_detected() {
+        let symmetric =
+            InstanceData::from_distance_matrix(&[vec![0, 1, 2], vec![1, 0, 3], vec![2, 3, 0]]);
+        assert!(symmetric.is_symmetric());
+        assert_eq!(symmetric.distance(2, 1), 3);
+        assert_eq!(symmetric.distance(1, 1), u32::MAX);
+        let asymmetric = InstanceData::from_distance_matrix(&[vec![0, 1], vec![5, 0]]);
+        assert!(!asymmetric.is_symmetric());
+        assert_eq!(asymmetric.distance(0, 1), 1);
+        assert_eq!(asymmetric.distance(1, 0), 5);
+    }
+
+    #[test]
+    fn grid_nearest_neighbours_match_distance_matrix() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let coordinates: Vec<(f64, f64)> = (0..300)
+            .map(|_| (rng.gen_range(0.0, 1000.0), rng.gen_range(0.0, 100.0)))
+            .collect();
+        let stored = InstanceData::euc_2d(&coordinates, true);
+        let computed = InstanceData::euc_2d(&coordinates, false);
+        assert_eq!(stored.distance(5, 17), computed.distance(5, 17));
+        assert_eq!(computed.distance(5, 5), u32::MAX);
+        let from_matrix = stored.nearest_neighbours(8);
+        let from_grid = computed.nearest_neighbours(8);
+        for i in 0..coordinates.len() {
+            // compare distances, as cities at equal distance may come in any order
+            let distances = |lists: &CandidateLists| -> Vec<u32> {
+                lists
+                    .of(i)
+                    .iter()
+                    .map(|&j| stored.distance(i, j as usize))
+                    .collect()
+            };
+            assert_eq!(distances(&from_matrix), distances(&from_grid), "city {}", i);
+        }
+    }
+
+    #[test]
+    fn large_3d_instances_compute_distances_on_demand() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let coordinates: Vec<(f64, f64, f64)> = (0..=MAX_MATRIX_SIZE)
+            .map(|_| {
+                (
+                    rng.gen_range(0.0, 1000.0),
+                    rng.gen_range(0.0, 1000.0),
+                    rng.gen_range(0.0, 1000.0),
+                )
+            })
+            .collect();
+        let computed = InstanceData::from_3d_coordinates(&coordinates);
+        assert!(matches!(computed.distances, Distances::Euc3d(_)));
+        assert!(computed.is_symmetric());
+        let stored = InstanceData::from_3d_coordinates(&coordinates[..50]);
+        assert!(matches!(stored.distances, Distances::Symmetric(_)));
+        for i in 0..50 {
+            for j in 0..50 {
+                assert_eq!(stored.distance(i, j), computed.distance(i, j));
+            }
+        }
+    }
 }
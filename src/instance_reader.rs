@@ -0,0 +1,527 @@
+//! Reading instances from files in formats other than TSPLIB: CSV files of
+//! coordinates, JSON instances and plain distance matrices.
+//!
+//! Cities are numbered in the order the file lists them, whatever ids it gives
+//! them.
+
+use crate::instance_data::{Coordinates, EdgeWeightType, FileData, InstanceData, Metadata};
+use crate::tsplibreader::read_instance;
+use serde_derive::Deserialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Format of an instance file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstanceFormat {
+    Tsplib,
+    /// A city per row, as `id,x,y` or `id,lat,lon`
+    Csv,
+    Json,
+    /// Rows of distances separated by whitespace or commas
+    Matrix,
+}
+
+impl InstanceFormat {
+    /// Format of a file judging by its extension: `.csv`, `.json`, or `.matrix`,
+    /// `.mat` and `.dist` for distance matrices. Anything else is taken to be
+    /// TSPLIB.
+    pub fn from_path(path: &Path) -> InstanceFormat {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "csv" => InstanceFormat::Csv,
+            "json" => InstanceFormat::Json,
+            "matrix" | "mat" | "dist" => InstanceFormat::Matrix,
+            _ => InstanceFormat::Tsplib,
+        }
+    }
+}
+
+impl FromStr for InstanceFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tsplib" => Ok(InstanceFormat::Tsplib),
+            "csv" => Ok(InstanceFormat::Csv),
+            "json" => Ok(InstanceFormat::Json),
+            "matrix" => Ok(InstanceFormat::Matrix),
+            _ => Err(format!("unknown instance format {}", s)),
+        }
+    }
+}
+
+/// Reads an instance in the given format, or in the format its extension
+/// suggests if none is given.
+pub fn read_instance_file_as(f_name: &str, format: Option<InstanceFormat>) -> FileData {
+    let path = Path::new(f_name);
+    let format = format.unwrap_or_else(|| InstanceFormat::from_path(path));
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let f = BufReader::new(File::open(f_name).expect("Failed opening file"));
+    match format {
+        InstanceFormat::Tsplib => read_instance(f),
+        InstanceFormat::Csv => read_csv_coordinates(f, &name),
+        InstanceFormat::Json => read_json_instance(f, &name),
+        InstanceFormat::Matrix => read_distance_matrix(f, &name),
+    }
+}
+
+/// Lines of a file that hold data, leaving out empty lines and `#` comments.
+fn data_lines(input: impl BufRead) -> impl Iterator<Item = String> {
+    input
+        .lines()
+        .map(|line| line.expect("failed reading instance"))
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+}
+
+/// Reads a CSV file with a city per row. A header row names the columns, which
+/// may be `x` and `y` (and `z`) for rounded euclidean distances, or `lat` and `lon`
+/// in decimal degrees for great-circle distances in kilometres. Other columns, such
+/// as an id, are ignored. Without a header the columns are `id,x,y`.
+pub fn read_csv_coordinates(input: impl BufRead, name: &str) -> FileData {
+    let mut lines = data_lines(input).peekable();
+    let split = |line: &str| -> Vec<String> {
+        line.split(',')
+            .map(|field| field.trim().to_lowercase())
+            .collect()
+    };
+    const COLUMNS: [&str; 10] = [
+        "x",
+        "y",
+        "z",
+        "lat",
+        "latitude",
+        "lon",
+        "lng",
+        "long",
+        "longitude",
+        "id",
+    ];
+    let header = match lines.peek() {
+        Some(line) if split(line).iter().any(|f| COLUMNS.contains(&f.as_str())) => {
+            let header = split(line);
+            lines.next();
+            header
+        }
+        _ => vec!["id".to_owned(), "x".to_owned(), "y".to_owned()],
+    };
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let rows: Vec<Vec<String>> = lines.map(|line| split(&line)).collect();
+    // only the columns that are used have to hold numbers
+    let values = |index: usize| -> Vec<f64> {
+        rows.iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let field = row
+                    .get(index)
+                    .unwrap_or_else(|| panic!("CSV row {} has no {} column", i + 1, header[index]));
+                match field.parse::<f64>() {
+                    Ok(value) if value.is_finite() => value,
+                    _ => panic!("invalid {} {} in CSV row {}", header[index], field, i + 1),
+                }
+            })
+            .collect()
+    };
+
+    let lat_lon = (
+        column(&["lat", "latitude"]),
+        column(&["lon", "lng", "long", "longitude"]),
+    );
+    let (data, coordinates, edge_weight_type) = match (column(&["x"]), column(&["y"]), lat_lon) {
+        (Some(x), Some(y), _) => match column(&["z"]) {
+            Some(z) => {
+                let nodes: Vec<(f64, f64, f64)> = values(x)
+                    .into_iter()
+                    .zip(values(y))
+                    .zip(values(z))
+                    .map(|((x, y), z)| (x, y, z))
+                    .collect();
+                let data = InstanceData::from_3d_coordinates(&nodes);
+                (
+                    data,
+                    Coordinates::ThreeD(nodes),
+                    Some(EdgeWeightType::EUC_3D),
+                )
+            }
+            None => {
+                let nodes: Vec<(f64, f64)> = values(x).into_iter().zip(values(y)).collect();
+                let data = InstanceData::from_coordinates(&nodes);
+                (data, Coordinates::TwoD(nodes), Some(EdgeWeightType::EUC_2D))
+            }
+        },
+        (_, _, (Some(lat), Some(lon))) => {
+            let nodes: Vec<(f64, f64)> = values(lat).into_iter().zip(values(lon)).collect();
+            let data = InstanceData::from_geographic_coordinates(&nodes);
+            (data, Coordinates::Geographic(nodes), None)
+        }
+        _ => panic!("CSV header has neither x and y nor lat and lon columns"),
+    };
+    let mut file_data = file_data(name, None, data, edge_weight_type);
+    file_data.coordinates = Some(coordinates);
+    file_data
+}
+
+/// An instance as described in a JSON file. Exactly one of `coordinates`,
+/// `lat_lon` and `distances` gives the distances between cities.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonInstance {
+    name: Option<String>,
+    comment: Option<String>,
+    /// [x, y] or [x, y, z] of each city, for rounded euclidean distances
+    coordinates: Option<Vec<Vec<f64>>>,
+    /// [latitude, longitude] of each city in decimal degrees, for great-circle
+    /// distances in kilometres
+    lat_lon: Option<Vec<(f64, f64)>>,
+    /// Distance from each city to every other one, rounded to whole numbers.
+    /// Row `i` has the distances from city `i`, which may differ from the
+    /// distances to it.
+    distances: Option<Vec<Vec<f64>>>,
+    /// [x, y] to draw each city at, for instances given by their distances
+    display: Option<Vec<(f64, f64)>>,
+}
+
+/// Reads an instance from a JSON object such as
+/// `{"name": "square", "coordinates": [[0, 0], [0, 10], [10, 10], [10, 0]]}`.
+/// The name defaults to `name`.
+pub fn read_json_instance(input: impl Read, name: &str) -> FileData {
+    let instance: JsonInstance =
+        serde_json::from_reader(input).expect("Could not read instance from JSON");
+    let name = instance.name.as_deref().unwrap_or(name);
+    let comment = instance.comment;
+    let mut file_data = match (instance.coordinates, instance.lat_lon, instance.distances) {
+        (Some(nodes), None, None) if nodes.iter().all(|node| node.len() == 3) => {
+            let nodes: Vec<(f64, f64, f64)> = nodes.iter().map(|n| (n[0], n[1], n[2])).collect();
+            let data = InstanceData::from_3d_coordinates(&nodes);
+            let mut file_data = file_data(name, comment, data, Some(EdgeWeightType::EUC_3D));
+            file_data.coordinates = Some(Coordinates::ThreeD(nodes));
+            file_data
+        }
+        (Some(nodes), None, None) => {
+            assert!(
+                nodes.iter().all(|node| node.len() == 2),
+                "coordinates must all be [x, y] or all [x, y, z]"
+            );
+            let nodes: Vec<(f64, f64)> = nodes.iter().map(|n| (n[0], n[1])).collect();
+            let data = InstanceData::from_coordinates(&nodes);
+            let mut file_data = file_data(name, comment, data, Some(EdgeWeightType::EUC_2D));
+            file_data.coordinates = Some(Coordinates::TwoD(nodes));
+            file_data
+        }
+        (None, Some(nodes), None) => {
+            let data = InstanceData::from_geographic_coordinates(&nodes);
+            let mut file_data = file_data(name, comment, data, None);
+            file_data.coordinates = Some(Coordinates::Geographic(nodes));
+            file_data
+        }
+        (None, None, Some(distances)) => {
+            let distances: Vec<Vec<u32>> = distances.iter().map(|row| round_row(row)).collect();
+            matrix_file_data(name, comment, &distances)
+        }
+        _ => panic!("JSON instance must have exactly one of coordinates, lat_lon and distances"),
+    };
+    if let Some(display) = instance.display {
+        assert_eq!(
+            display.len(),
+            file_data.data.size,
+            "display must have a point for every city"
+        );
+        file_data.display_data = Some(display);
+    }
+    file_data
+}
+
+/// Reads a square matrix with the distances from each city to every other one,
+/// a row per line, separated by whitespace or commas. Distances are rounded to
+/// whole numbers. Row `i` has the distances from city `i`, so matrices that
+/// differ by direction give asymmetric instances.
+///
+/// A first line of column names, such as `a,b,c` or `id,1,2,3`, is skipped, and
+/// so is a first column of city ids. They are told apart from distances by the
+/// number of rows and columns: with `n` cities there are `n` rows of distances,
+/// each `n` or, with ids, `n + 1` fields wide. A header of numbers over a column
+/// of ids is as wide and as long as a matrix without them, so a first row of
+/// numbers is only read as distances if its distance from the city to itself
+/// is 0 and it doesn't look like the ids of the first column. Labelling the
+/// header, as in `id,1,2,3`, avoids any doubt.
+pub fn read_distance_matrix(input: impl BufRead, name: &str) -> FileData {
+    let split = |line: &str| -> Vec<String> {
+        line.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|field| !field.is_empty())
+            .map(str::to_owned)
+            .collect()
+    };
+    let mut rows: Vec<Vec<String>> = data_lines(input).map(|line| split(&line)).collect();
+    let has_header = match rows.as_slice() {
+        [first, second, ..] => {
+            first.iter().any(|f| f.parse::<f64>().is_err())
+                // a header that is all numbers is told apart by its width, or by
+                // making the matrix one row longer than it is wide
+                || first.len() != second.len()
+                || rows.iter().all(|row| row.len() + 1 == rows.len())
+        }
+        _ => false,
+    };
+    if has_header {
+        let header = rows.remove(0);
+        assert!(
+            header.len() == rows.len() || header.len() == rows.len() + 1,
+            "distance matrix header does not have a column for each of its {} rows",
+            rows.len()
+        );
+    }
+    let size = rows.len();
+    let has_ids = rows.first().is_some_and(|row| row.len() == size + 1);
+    let distances: Vec<Vec<u32>> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            assert_eq!(
+                row.len(),
+                size + has_ids as usize,
+                "row {} of the distance matrix does not have a distance for each of its {} rows",
+                i + 1,
+                size
+            );
+            let row: Vec<f64> = row[has_ids as usize..]
+                .iter()
+                .map(|field| {
+                    field
+                        .parse()
+                        .unwrap_or_else(|_| panic!("invalid distance {} in row {}", field, i + 1))
+                })
+                .collect();
+            round_row(&row)
+        })
+        .collect();
+    if !has_header {
+        check_first_row(&rows, has_ids);
+    }
+    matrix_file_data(name, None, &distances)
+}
+
+/// Panics if the first row of a matrix without a header could be a header of
+/// numbers instead of the distances from the first city.
+fn check_first_row(rows: &[Vec<String>], has_ids: bool) {
+    let first = match rows.first() {
+        Some(first) if first.len() > has_ids as usize => first,
+        _ => return,
+    };
+    let to_itself = &first[has_ids as usize];
+    if to_itself.parse::<f64>().ok() != Some(0.0) {
+        panic!(
+            "the first row of the distance matrix has a distance of {} from its city to \
+             itself, so it may be a header; label its columns, as in id,1,2,3",
+            to_itself
+        );
+    }
+    // a header over a column of ids, such as 0,1,2,3 over rows 1,0,5,7 and so on
+    let column: Vec<&String> = rows.iter().skip(1).map(|row| &row[0]).collect();
+    let labels: Vec<&String> = first.iter().skip(1).collect();
+    let consecutive = labels
+        .iter()
+        .map(|label| label.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .is_ok_and(|numbers| numbers.windows(2).all(|pair| pair[1] == pair[0] + 1));
+    if !has_ids && labels.len() >= 3 && labels == column && consecutive {
+        panic!(
+            "the first row and column of the distance matrix look like city ids, so it \
+             may have a header and a column of ids; label the header's columns, as in \
+             id,1,2,3, or the cities, as in a,b,c"
+        );
+    }
+}
+
+fn round_row(row: &[f64]) -> Vec<u32> {
+    row.iter()
+        .map(|&d| {
+            assert!(d >= 0.0 && d.is_finite(), "invalid distance {}", d);
+            d.round() as u32
+        })
+        .collect()
+}
+
+fn matrix_file_data(name: &str, comment: Option<String>, distances: &[Vec<u32>]) -> FileData {
+    let data = InstanceData::from_distance_matrix(distances);
+    let mut file_data = file_data(name, comment, data, Some(EdgeWeightType::EXPLICIT));
+    file_data.metadata.edge_weight_format = Some("FULL_MATRIX".to_owned());
+    file_data
+}
+
+/// Instance with the metadata a TSPLIB file would have given it. Instances with
+/// geographic coordinates have no edge weight type, as TSPLIB has none for
+/// great-circle distances from decimal degrees.
+fn file_data(
+    name: &str,
+    comment: Option<String>,
+    data: InstanceData,
+    edge_weight_type: Option<EdgeWeightType>,
+) -> FileData {
+    assert!(data.size > 0, "instance {} has no cities", name);
+    let problem_type = if data.is_symmetric() { "TSP" } else { "ATSP" };
+    FileData {
+        metadata: Metadata {
+            name: name.to_owned(),
+            problem_type: Some(problem_type.to_owned()),
+            comment,
+            dimension: Some(data.size),
+            edge_weight_type,
+            ..Metadata::default()
+        },
+        data,
+        coordinates: None,
+        display_data: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_is_chosen_by_extension() {
+        assert_eq!(
+            InstanceFormat::from_path(Path::new("depots.CSV")),
+            InstanceFormat::Csv
+        );
+        assert_eq!(
+            InstanceFormat::from_path(Path::new("cities.dist")),
+            InstanceFormat::Matrix
+        );
+        assert_eq!(
+            InstanceFormat::from_path(Path::new("a280.tsp")),
+            InstanceFormat::Tsplib
+        );
+    }
+
+    #[test]
+    fn csv_instances_are_read() {
+        let csv = "id,x,y\n# a comment\nA,0,0\nB,0,3\n\nC,4,3\n";
+        let instance = read_csv_coordinates(csv.as_bytes(), "tri");
+        assert_eq!(instance.metadata.dimension, Some(3));
+        assert_eq!(instance.data.distance(0, 2), 5);
+        let instance = read_csv_coordinates("A,0,0\nB,0,3\n".as_bytes(), "line");
+        assert_eq!(instance.data.distance(0, 1), 3);
+        let csv = "id,lat,lon\n1,10,20\n2,11,20\n";
+        let instance = read_csv_coordinates(csv.as_bytes(), "geo");
+        assert_eq!(instance.data.distance(0, 1), 111);
+        assert_eq!(
+            instance.display_coordinates(),
+            Some(vec![(20.0, 10.0), (20.0, 11.0)])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid y")]
+    fn csv_field_that_is_not_a_number_is_rejected() {
+        read_csv_coordinates("id,x,y\nA,0,0\nB,0,three\n".as_bytes(), "bad");
+    }
+
+    #[test]
+    #[should_panic(expected = "not a latitude and longitude")]
+    fn csv_latitude_out_of_range_is_rejected() {
+        read_csv_coordinates("id,lat,lon\n1,10,20\n2,91,20\n".as_bytes(), "bad");
+    }
+
+    #[test]
+    fn json_instances_are_read() {
+        let json = r#"{"name": "tri", "coordinates": [[0, 0], [0, 3], [4, 3]]}"#;
+        let instance = read_json_instance(json.as_bytes(), "file");
+        assert_eq!(instance.metadata.name, "tri");
+        assert_eq!(instance.data.distance(2, 0), 5);
+        let json = r#"{"distances": [[0, 1.4], [5, 0]], "display": [[0, 0], [1, 1]]}"#;
+        let instance = read_json_instance(json.as_bytes(), "file");
+        assert_eq!(instance.metadata.name, "file");
+        assert_eq!(instance.metadata.problem_type.as_deref(), Some("ATSP"));
+        assert_eq!(instance.data.distance(0, 1), 1);
+        assert_eq!(instance.display_data, Some(vec![(0.0, 0.0), (1.0, 1.0)]));
+    }
+
+    #[test]
+    #[should_panic(expected = "exactly one of coordinates, lat_lon and distances")]
+    fn json_with_two_distance_sources_is_rejected() {
+        let json = r#"{"coordinates": [[0, 0], [0, 3]], "distances": [[0, 3], [3, 0]]}"#;
+        read_json_instance(json.as_bytes(), "file");
+    }
+
+    #[test]
+    fn matrix_instances_are_read() {
+        let matrix = "a b c\n0 1 2\n1, 0, 3\n2\t3\t0\n";
+        let instance = read_distance_matrix(matrix.as_bytes(), "m");
+        assert!(instance.data.is_symmetric());
+        assert_eq!(instance.data.distance(2, 1), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not have a distance for each")]
+    fn ragged_matrix_is_rejected() {
+        read_distance_matrix("0 1 2\n1 0 3\n2 3\n".as_bytes(), "m");
+    }
+
+    #[test]
+    fn matrix_headers_and_ids_are_told_apart_by_width() {
+        let matrices = [
+            "id,1,2,3\n1,0,5,7\n2,5,0,8\n3,7,8,0\n",
+            "1,2,3\n1,0,5,7\n2,5,0,8\n3,7,8,0\n",
+            "1,0,5,7\n2,5,0,8\n3,7,8,0\n",
+            "1,2,3\n0,5,7\n5,0,8\n7,8,0\n",
+            "0,5,7\n5,0,8\n7,8,0\n",
+        ];
+        for matrix in matrices.iter() {
+            let instance = read_distance_matrix(matrix.as_bytes(), "m");
+            assert_eq!(instance.data.size, 3, "{}", matrix);
+            assert_eq!(instance.data.distance(0, 1), 5, "{}", matrix);
+            assert_eq!(instance.data.distance(2, 1), 8, "{}", matrix);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "look like city ids")]
+    fn numeric_header_over_ids_is_rejected() {
+        read_distance_matrix("0,1,2,3\n1,0,5,7\n2,5,0,8\n3,7,8,0\n".as_bytes(), "m");
+    }
+
+    #[test]
+    #[should_panic(expected = "distance of 4 from its city to itself")]
+    fn first_row_that_is_not_distances_is_rejected() {
+        read_distance_matrix("4,5,6\n0,5,7\n5,0,8\n".as_bytes(), "m");
+    }
+
+    #[test]
+    #[should_panic(expected = "has no cities")]
+    fn csv_without_cities_is_rejected() {
+        read_csv_coordinates("id,x,y\n".as_bytes(), "empty");
+    }
+
+    #[test]
+    #[should_panic(expected = "has no cities")]
+    fn json_without_cities_is_rejected() {
+        read_json_instance(r#"{"coordinates": []}"#.as_bytes(), "empty");
+    }
+
+    #[test]
+    fn asymmetric_matrices_are_solved_by_direction() {
+        use crate::aco::{run_aco, AcoParameters, Algorithm, RunOptions};
+        let matrix = "0 1 9 9\n9 0 1 9\n9 9 0 1\n1 9 9 0\n";
+        let instance = read_distance_matrix(matrix.as_bytes(), "cycle");
+        assert_eq!(instance.metadata.problem_type.as_deref(), Some("ATSP"));
+        assert_eq!(instance.data.distance(0, 1), 1);
+        assert_eq!(instance.data.distance(1, 0), 9);
+        let parameters = AcoParameters::builder(Algorithm::Acs)
+            .num_ants(4)
+            .max_iterations(20)
+            .seed(2)
+            .build();
+        let results = run_aco(&instance.data, &parameters, RunOptions::default());
+        assert_eq!(results.best_length(), 4);
+        let parameters = AcoParameters::builder(Algorithm::Acs).candidates(2).build();
+        assert!(parameters.check_instance(&instance.data).is_err());
+    }
+}
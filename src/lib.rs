@@ -0,0 +1,46 @@
+//! Single core and multicore implementations of the MMAS and ACS metaheuristics
+//! for the Travelling Salesman Problem.
+//!
+//! An instance can be read from a TSPLIB file with `tsplibreader::read_instance_file`,
+//! from CSV, JSON or distance matrix files with `instance_reader::read_instance_file_as`,
+//! or built directly with `InstanceData::from_distance_matrix` or
+//! `InstanceData::from_coordinates`. Parameters are built with
+//! `AcoParameters::builder`, and `run_aco` runs the chosen `Algorithm` on the
+//! instance, returning the `ResultLog` of the run.
+
+pub mod aco;
+pub mod instance_data;
+pub mod instance_reader;
+pub mod tsplibreader;
+
+// Used by the command-line program, but not part of the library's API.
+#[doc(hidden)]
+pub mod generator;
+#[doc(hidden)]
+pub mod logger;
+#[doc(hidden)]
+pub mod parameters_reader;
+#[doc(hidden)]
+pub mod progress;
+#[doc(hidden)]
+pub mod result_file;
+#[doc(hidden)]
+pub mod run_time_distribution;
+#[doc(hidden)]
+pub mod stats;
+#[doc(hidden)]
+pub mod svg;
+#[doc(hidden)]
+pub mod timer;
+#[doc(hidden)]
+pub mod tsplibwriter;
+#[doc(hidden)]
+pub mod util;
+
+#[cfg(test)]
+mod test_support;
+
+pub use crate::aco::{
+    run_aco, AcoParameters, AcoParametersBuilder, Algorithm, ResultLog, RunOptions,
+};
+pub use crate::instance_data::InstanceData;
@@ -1,43 +1,78 @@
-use std::time;
-use log::info;
 use itertools::Itertools;
+use log::{debug, error, info};
+use std::time::{Duration, Instant};
 
-use crate::aco::AntResult;
+use crate::aco::Ant;
+use crate::util::TourError;
 
 pub enum OutputType {
     StdOut,
     File(String),
-    StdAndFile(String)
+    StdAndFile(String),
 }
 
-
-pub fn setup_logger(output: OutputType) -> Result<(), fern::InitError> {
+/// Sets up the global logger. Every message is prefixed with the time since the
+/// logger was set up and the name of the thread it came from, which tells apart
+/// runs executing concurrently.
+pub fn setup_logger(output: OutputType, level: log::LevelFilter) -> Result<(), fern::InitError> {
+    let start = Instant::now();
     let f = fern::Dispatch::new()
-        .format(|out, message, record| {
+        .format(move |out, message, record| {
+            let elapsed = start.elapsed();
             out.finish(format_args!(
-                "{}",
+                "[{:>5}.{:03}s {:<5} {}] {}",
+                elapsed.as_secs(),
+                elapsed.subsec_millis(),
+                record.level(),
+                std::thread::current().name().unwrap_or("unnamed"),
                 message
             ))
         })
-        .level(log::LevelFilter::Debug);
+        .level(level);
 
-    use self::OutputType::*;    
+    use self::OutputType::*;
     match output {
         StdOut => f.chain(std::io::stdout()),
         File(s) => f.chain(fern::log_file(s)?),
-        StdAndFile(s) => f.chain(std::io::stdout()).chain(fern::log_file(s)?),                
-    }.apply()?;
+        StdAndFile(s) => f.chain(std::io::stdout()).chain(fern::log_file(s)?),
+    }
+    .apply()?;
     Ok(())
 }
 
-pub fn log_start() {
-    info!("---NEW RUN---");
+pub fn log_run_start(label: &str) {
+    info!("---NEW RUN--- {}", label);
+}
+
+pub fn log_run_end(label: &str, best_length: u32, elapsed: Duration) {
+    info!(
+        "---RUN FINISHED--- {} best: {} in {}.{:03}s",
+        label,
+        best_length,
+        elapsed.as_secs(),
+        elapsed.subsec_millis()
+    );
+}
+
+pub fn log_iteration(iteration: usize, best_this_iter: u32, best_so_far: u32) {
+    debug!(
+        "#Iter {} best: {} best so far: {}",
+        iteration, best_this_iter, best_so_far
+    );
+}
+
+pub fn log_new_best(iteration: usize, ant: &Ant) {
+    info!(
+        "!NEW BEST! VALUE: {} on iteration {}",
+        ant.length, iteration
+    );
+    debug!("→{}", ant.tour.iter().map(|i| i + 1).format(" "));
 }
 
-pub fn log_new_iter() {
-    info!("#NewIter");
+pub fn log_invalid_tour(error: &TourError) {
+    error!("best tour of the run is invalid: {}", error);
 }
-    
-pub fn log_new_best(ant: &AntResult) {
-    info!("!NEW BEST! VALUE: {}\n→{}", ant.value, ant.tour.iter().format(" "));        
+
+pub fn log_restart(iteration: usize) {
+    info!("trails reinitialized on iteration {}", iteration);
 }
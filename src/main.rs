@@ -1,63 +1,276 @@
-mod aco;
-mod instance_data;
-mod parameters_reader;
-mod timer;
-mod tsplibreader;
-mod util;
-
-use crate::aco::run_aco;
-use crate::aco::ResultLog;
-use crate::tsplibreader::read_instance_file;
-use clap::{App, Arg};
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::{BufWriter, Result};
+mod compare;
+mod generate;
+mod manifest;
+mod plot;
+mod report;
+mod rtd;
+mod validate;
+
+#[cfg(test)]
+mod test_support;
+
+use crate::manifest::Manifest;
+use clap::{App, AppSettings, Arg, ArgMatches};
+use log::{info, warn};
+use parallelized_acotsp::aco::{
+    Ant, Checkpoint, CheckpointConfig, ColonyRegistry, Control, Observer,
+};
+use parallelized_acotsp::instance_data::FileData;
+use parallelized_acotsp::instance_reader::{read_instance_file_as, InstanceFormat};
+use parallelized_acotsp::logger::{self, OutputType};
+use parallelized_acotsp::parameters_reader::{self, RunDescription};
+use parallelized_acotsp::progress::ProgressDisplay;
+use parallelized_acotsp::result_file::write_result_file;
+use parallelized_acotsp::timer::Stopwatch;
+use parallelized_acotsp::tsplibreader::read_tour_file;
+use parallelized_acotsp::tsplibwriter::write_tour_file;
+use parallelized_acotsp::{run_aco, RunOptions};
+use parking_lot::Mutex;
+use rand::Rng;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Path of the result file for a given run of a description, as
+/// `<out_path>/<out_path with / replaced by _>_<algorithm>_<run>.txt`. If an
+/// earlier description would use the same path, the description's index is
+/// included to tell them apart, as in `..._<algorithm>_d<index>_<run>.txt`.
+fn out_file_path(
+    description: &RunDescription,
+    index: usize,
+    run: usize,
+    include_index: bool,
+) -> PathBuf {
+    let prefix = format!(
+        "{}_{}",
+        description.out_path.replace('/', "_"),
+        description.parameters.algorithm
+    );
+    let out_file = if include_index {
+        format!("{}_d{}_{}.txt", prefix, index, run)
+    } else {
+        format!("{}_{}.txt", prefix, run)
+    };
+    [description.out_path.as_str(), &out_file].iter().collect()
+}
+
+/// Option of the subcommands that read an instance, to give the format of its
+/// file instead of going by its extension.
+fn format_arg() -> Arg<'static, 'static> {
+    Arg::with_name("Format")
+        .long("format")
+        .takes_value(true)
+        .possible_values(&["tsplib", "csv", "json", "matrix"])
+        .help("Format of the instance file (default: by its extension, .csv, .json, .matrix, .mat or .dist, and TSPLIB for any other)")
+}
+
+fn instance_format(matches: &ArgMatches) -> Option<InstanceFormat> {
+    matches
+        .value_of("Format")
+        .map(|f| f.parse().expect("invalid instance format"))
+}
+
+/// Whether an earlier description in the run file writes its results to the
+/// same files as the description at `index`, so that its index must be in their
+/// names. The first of them keeps the plain names, so adding descriptions to the
+/// end of a run file never renames the results of the ones already there.
+fn shares_out_file(descriptions: &[RunDescription], index: usize) -> bool {
+    let description = &descriptions[index];
+    descriptions[..index].iter().any(|d| {
+        d.out_path == description.out_path
+            && d.parameters.algorithm == description.parameters.algorithm
+    })
+}
+
+/// A single run of a description that still has to be executed.
+struct Job<'a> {
+    /// Index of the description in the run file
+    index: usize,
+    description: &'a RunDescription,
+    run: usize,
+    out_file: PathBuf,
+    /// Length of an optimal tour, from the description or its optimal tour file
+    optimum: Option<u32>,
+    /// Read from the description's tour file before any job starts
+    initial_tour: Option<Ant>,
+}
+
+struct JobOptions {
+    resume: bool,
+    progress: Option<ProgressDisplay>,
+    stop_file: Option<PathBuf>,
+}
+
+/// Stops a run early once a given file exists.
+struct StopFile(PathBuf);
+
+impl Observer for StopFile {
+    fn on_iteration_start(&mut self, _: usize, _: Duration) -> Control {
+        if self.0.exists() {
+            Control::Stop
+        } else {
+            Control::Continue
+        }
+    }
+}
 
-fn print_log(results: ResultLog, out_path: &str, file_name: &str, print_tour: bool) -> Result<()> {
-    std::fs::create_dir_all(out_path).expect("failed at creating path");
-    let out: PathBuf = [out_path, file_name].iter().collect();
-    let f = File::create(out.as_path())?;
-    let mut writer = BufWriter::new(f);    
-    let best = results.best_timestamped();
-    writeln!(writer, "BEST FOUND: {}", best.result.length)?;
-    writeln!(
-        writer,
-        "BEST TOUR: {:?}",
-        best.result
-            .tour
-            .iter()
-            .map(|i| i + 1)
-            .collect::<Vec<usize>>()
-    )?;
-
-    writeln!(
-        writer,
-        "Found on iteration {} at {}.{}s",
-        best.iteration,
-        best.timestamp.as_secs(),
-        best.timestamp.subsec_millis()
-    )?;
-    writeln!(writer, "==========================")?;
-    for (i, t) in results.log.iter().enumerate() {
-        writeln!(writer, "-----Iter {}, new_best: {}", i + 1, t.is_new_best)?;
-        writeln!(
-            writer,
-            "length: {} time {}.{}s",
-            t.result.length,
-            t.timestamp.as_secs(),
-            t.timestamp.subsec_millis()
-        )?;
-        if print_tour {
-            writeln!(
-                writer,
-                "tour: {:?}",
-                t.result.tour.iter().map(|i| i + 1).collect::<Vec<usize>>()
-            )?;
+/// Reads a tour file given in a run description.
+///
+/// Panics if it is not a tour of the instance.
+fn read_tour_of(f_name: &str, instance: &FileData) -> Ant {
+    read_tour_file(f_name)
+        .to_ant(&instance.data)
+        .unwrap_or_else(|e| {
+            panic!(
+                "{} is not a tour of {}: {}",
+                f_name, instance.metadata.name, e
+            )
+        })
+}
+
+/// Comment of the `.tour` file of a run.
+fn tour_comment(length: u32, algorithm: &str, seed: u64) -> String {
+    format!(
+        "Length {}, found by {} with seed {}",
+        length, algorithm, seed
+    )
+}
+
+/// Rewrites the `.tour` file of a run every time a new best tour is found.
+struct TourWriter {
+    path: PathBuf,
+    algorithm: String,
+    seed: u64,
+}
+
+impl Observer for TourWriter {
+    fn on_new_best(&mut self, _: usize, best: &Ant, _: Duration) -> Control {
+        let comment = tour_comment(best.length, &self.algorithm, self.seed);
+        // the tour is written again once the run is over, so a failure here only
+        // leaves the file behind for a while
+        if let Err(e) = write_tour_file(&self.path, &comment, &best.tour) {
+            warn!("failed writing {}: {}", self.path.display(), e);
         }
-        //
+        Control::Continue
+    }
+}
+
+fn execute_job(job: &Job, instance: &FileData, options: &JobOptions, manifest: &Mutex<Manifest>) {
+    let description = job.description;
+    let label = format!(
+        "[{} on {}, run {} of {}]",
+        description.parameters.algorithm, instance.metadata.name, job.run, description.num_runs
+    );
+    if options.stop_file.as_ref().is_some_and(|f| f.exists()) {
+        info!("{} skipped, stop file exists", label);
+        return;
+    }
+    logger::log_run_start(&label);
+    let checkpoint_path = job.out_file.with_extension("txt.checkpoint");
+    let checkpoint = description
+        .checkpoint_interval
+        .map(|secs| CheckpointConfig {
+            path: checkpoint_path.clone(),
+            interval: Duration::from_secs(secs),
+        });
+    let resume = if options.resume && checkpoint_path.exists() {
+        info!("{} resuming from {}", label, checkpoint_path.display());
+        Some(Checkpoint::load(&checkpoint_path).expect("failed reading checkpoint"))
+    } else {
+        None
+    };
+    // chosen here rather than by the colony, so that the tour files written
+    // during the run can name it
+    let mut parameters = description.parameters.clone();
+    let seed = match &resume {
+        Some(checkpoint) => checkpoint.result_log.seed,
+        None => parameters.seed.unwrap_or_else(|| rand::thread_rng().gen()),
+    };
+    parameters.seed = Some(seed);
+    let optimum = job.optimum;
+    let progress = options.progress.as_ref().map(|display| {
+        display.add_run(
+            label.clone(),
+            optimum,
+            description.parameters.time_limit,
+            description.parameters.max_iterations,
+        )
+    });
+    let stopwatch = Stopwatch::new();
+    let tour_file = job.out_file.with_extension("tour");
+    let algorithm = description.parameters.algorithm.to_string();
+    let mut observers: Vec<Box<dyn Observer>> = Vec::new();
+    if let Some(stop_file) = &options.stop_file {
+        observers.push(Box::new(StopFile(stop_file.clone())));
+    }
+    // instances without coordinates were rejected before any run started
+    let snapshot_coordinates = if description.snapshot_iterations.is_empty() {
+        None
+    } else {
+        instance.display_coordinates()
+    };
+    if let Some(coordinates) = snapshot_coordinates {
+        if let Some(dir) = job.out_file.parent() {
+            std::fs::create_dir_all(dir).expect("failed at creating path");
+        }
+        observers.push(Box::new(plot::SnapshotPlotter {
+            coordinates,
+            iterations: description.snapshot_iterations.clone(),
+            path: job.out_file.clone(),
+            label: label.clone(),
+            best: None,
+        }));
+    }
+    if description.rewrite_best_tour {
+        if let Some(dir) = tour_file.parent() {
+            std::fs::create_dir_all(dir).expect("failed at creating path");
+        }
+        observers.push(Box::new(TourWriter {
+            path: tour_file.clone(),
+            algorithm: algorithm.clone(),
+            seed,
+        }));
+    }
+    let run_options = RunOptions {
+        checkpoint,
+        resume,
+        progress,
+        observers,
+        initial_tour: job.initial_tour.clone(),
+    };
+    let results = run_aco(&instance.data, &parameters, run_options);
+    if results.stopped {
+        // the run is neither written nor marked as completed, so it is executed
+        // again by the next invocation, from its checkpoint if there is one
+        if checkpoint_path.exists() {
+            info!(
+                "{} stopped early, its checkpoint is kept for --resume",
+                label
+            );
+        } else {
+            info!("{} stopped early, results discarded", label);
+        }
+        return;
+    }
+    if results.verify(&instance.data).is_err() {
+        // run_aco has already logged the error. The results are not written
+        // at all, so that report and compare can't count them
+        return;
+    }
+    logger::log_run_end(&label, results.best_length(), stopwatch.elapsed());
+    info!("{} printing results to {}", label, job.out_file.display());
+    let best = results.best_tour();
+    let comment = tour_comment(best.length, &algorithm, results.seed);
+    write_result_file(&results, &job.out_file, optimum).expect("failed writing log file");
+    write_tour_file(&tour_file, &comment, &best.tour).expect("failed writing tour file");
+    manifest
+        .lock()
+        .mark_completed(job.index, description, job.run, &job.out_file)
+        .expect("failed updating batch manifest");
+    if checkpoint_path.exists() {
+        std::fs::remove_file(&checkpoint_path).expect("failed removing checkpoint");
     }
-    Ok(())
 }
 
 fn main() {
@@ -68,52 +281,279 @@ fn main() {
                             .version("0.1")
                             .author("Iago Almeida <ialmeida@edu.unifor.br>")
                             .about("Single core and Multicore implementations of MMAS and ACS metaheuristics for the TSP")
+                            .setting(AppSettings::SubcommandsNegateReqs)
+                            .subcommand(validate::subcommand())
+                            .subcommand(plot::subcommand())
+                            .subcommand(report::subcommand())
+                            .subcommand(rtd::subcommand())
+                            .subcommand(compare::subcommand())
+                            .subcommand(generate::subcommand())
                             .arg(Arg::with_name("RUN DESCRIPTION FILE")
                                     .help("JSON file with the description of input files, parameters, number of runs, and algorithms to run")
                                     .required(true))
-                            .arg(Arg::with_name("Print Tour")
-                                    .short("t")
-                                    .long("tour")
+                            .arg(Arg::with_name("Resume")
+                                    .short("r")
+                                    .long("resume")
+                                    .takes_value(false)
+                                    .help("Continue runs from their checkpoints, if any were saved"))
+                            .arg(Arg::with_name("Fresh")
+                                    .long("fresh")
+                                    .takes_value(false)
+                                    .help("Execute all runs again, even ones already completed by a previous invocation"))
+                            .arg(Arg::with_name("Jobs")
+                                    .short("j")
+                                    .long("jobs")
+                                    .takes_value(true)
+                                    .help("Number of runs to execute at the same time (default 1)"))
+                            .arg(Arg::with_name("Log Level")
+                                    .long("log-level")
+                                    .takes_value(true)
+                                    .possible_values(&["error", "warn", "info", "debug", "trace"])
+                                    .help("Most verbose kind of message to log (default info). debug includes a summary of every iteration"))
+                            .arg(Arg::with_name("Log File")
+                                    .long("log-file")
+                                    .takes_value(true)
+                                    .help("File the progress log is also written to"))
+                            .arg(Arg::with_name("Quiet")
+                                    .short("q")
+                                    .long("quiet")
                                     .takes_value(false)
-                                    .help("Whether the tour itself should be printed "))
+                                    .requires("Log File")
+                                    .help("Don't write the progress log to stdout, only to the log file"))
+                            .arg(Arg::with_name("Progress")
+                                    .short("p")
+                                    .long("progress")
+                                    .takes_value(false)
+                                    .help("Show a live status line for each run. The log is then only written to the log file, or limited to warnings if there is none"))
+                            .arg(Arg::with_name("Stop File")
+                                    .long("stop-file")
+                                    .takes_value(true)
+                                    .help("Runs in progress stop early once this file exists, saving a checkpoint if their description asks for checkpoints. Stopped runs are not recorded as completed, so the next invocation executes them again, continuing from the checkpoint with --resume. Runs not started yet are skipped"))
                             .get_matches();
 
+    if let Some(matches) = matches.subcommand_matches("validate") {
+        let valid = validate::run(matches);
+        std::process::exit(if valid { 0 } else { 1 });
+    }
+    if let Some(matches) = matches.subcommand_matches("plot") {
+        plot::run(matches);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("report") {
+        report::run(matches);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("rtd") {
+        rtd::run(matches);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("compare") {
+        compare::run(matches);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("generate") {
+        generate::run(matches);
+        return;
+    }
+
+    let show_progress = matches.is_present("Progress");
+    let mut log_level = matches
+        .value_of("Log Level")
+        .unwrap_or("info")
+        .parse()
+        .expect("failed parsing log level");
+    // the progress display takes over the terminal, so the log only goes there if
+    // there is nowhere else to write it, and then only for warnings and errors
+    let log_output = match matches.value_of("Log File") {
+        Some(f) if matches.is_present("Quiet") || show_progress => OutputType::File(f.to_owned()),
+        Some(f) => OutputType::StdAndFile(f.to_owned()),
+        None => {
+            if show_progress {
+                log_level = std::cmp::min(log_level, log::LevelFilter::Warn);
+            }
+            OutputType::StdOut
+        }
+    };
+    logger::setup_logger(log_output, log_level).expect("failed setting up logger");
+
     let run_file_name = matches
         .value_of("RUN DESCRIPTION FILE")
         .expect("failed parsing argument");
-    let run_descriptions = crate::parameters_reader::read_run_file(&run_file_name);
-    for description in run_descriptions {
-        println!("STARTING NEW RUN of {:?}", description.parameters.algorithm);
-        println!("reading input file {}", &description.data_file);
-        timer::restart_timer();
-        let instance_file = read_instance_file(&description.data_file);
-        println!("read input in {}s", timer::elapsed().as_secs());
-        println!("---- starting runs");
+    let run_descriptions = parameters_reader::read_run_file(run_file_name);
+    // checked up front, since an unknown name would otherwise only be found once
+    // a worker starts that run, after every instance has been read
+    let registry = ColonyRegistry::with_builtin_colonies();
+    for (index, description) in run_descriptions.iter().enumerate() {
+        let algorithm = description.parameters.algorithm.name();
+        if !registry.contains(algorithm) {
+            panic!(
+                "unknown algorithm {} in run description {}, known colonies are: {}",
+                algorithm,
+                index,
+                registry.names().join(", ")
+            );
+        }
+    }
+    let mut manifest = Manifest::load_or_new(run_file_name);
+    if matches.is_present("Fresh") {
+        manifest.clear().expect("failed clearing batch manifest");
+    }
+    let num_jobs: usize = matches
+        .value_of("Jobs")
+        .unwrap_or("1")
+        .parse()
+        .expect("number of jobs must be a positive integer");
+    assert!(num_jobs > 0, "number of jobs must be a positive integer");
+    let options = JobOptions {
+        resume: matches.is_present("Resume"),
+        stop_file: matches.value_of("Stop File").map(PathBuf::from),
+        progress: if show_progress {
+            Some(ProgressDisplay::start())
+        } else {
+            None
+        },
+    };
+
+    let mut jobs = Vec::new();
+    for (index, description) in run_descriptions.iter().enumerate() {
+        let shares_out_file = shares_out_file(&run_descriptions, index);
         for run in 1..=description.num_runs {
-            println!("run {} of {}", run, description.num_runs);
-            timer::restart_timer();
-            let results = run_aco(&instance_file.data, &description.parameters);
-            println!("total elapsed time {}s", timer::elapsed().as_secs());
-            let out_file = format!(
-                "{}_{:?}_{}.txt",
-                description.out_path.replace('/', "_"),
-                description.parameters.algorithm,
-                run
+            let out_file = out_file_path(description, index, run, shares_out_file);
+            if manifest.is_completed(index, description, run, &out_file) {
+                info!(
+                    "skipping run {} of {} on {}, already completed",
+                    run, description.parameters.algorithm, &description.data_file
+                );
+                continue;
+            }
+            jobs.push(Job {
+                index,
+                description,
+                run,
+                out_file,
+                optimum: description.optimum,
+                initial_tour: None,
+            });
+        }
+    }
+
+    let mut instances = HashMap::new();
+    for job in jobs.iter() {
+        let data_file = job.description.data_file.as_str();
+        if !instances.contains_key(data_file) {
+            info!("reading input file {}", data_file);
+            let stopwatch = Stopwatch::new();
+            let instance_file = read_instance_file_as(data_file, job.description.format);
+            info!(
+                "read input {} in {}s",
+                instance_file.metadata.name,
+                stopwatch.elapsed().as_secs()
             );
-            println!(
-                "printing results to {}/{}",
-                &description.out_path, &out_file
+            instances.insert(data_file, instance_file);
+        }
+    }
+
+    // everything a job needs is checked before any of them starts, so that a bad
+    // run description doesn't stop the batch halfway through
+    let mut tours: HashMap<usize, (Option<u32>, Option<Ant>)> = HashMap::new();
+    for job in jobs.iter_mut() {
+        let description = job.description;
+        let data_file = description.data_file.as_str();
+        let instance = &instances[data_file];
+        let (optimum, initial_tour) = tours.entry(job.index).or_insert_with(|| {
+            let optimum = description.optimum.or_else(|| {
+                let f_name = description.optimal_tour.as_ref()?;
+                Some(read_tour_of(f_name, instance).length)
+            });
+            let initial_tour = description
+                .initial_tour
+                .as_ref()
+                .map(|f_name| read_tour_of(f_name, instance));
+            (optimum, initial_tour)
+        });
+        job.optimum = *optimum;
+        job.initial_tour = initial_tour.clone();
+        if let Err(e) = job.description.parameters.check_instance(&instance.data) {
+            panic!(
+                "run description {} can't run on {}: {}",
+                job.index, data_file, e
             );
-            print_log(
-                results,
-                &description.out_path,
-                &out_file,
-                matches.is_present("Print Tour"),
-            )
-            .expect("failed writing log file");
-            println!("-----")
         }
-        println!("==================");
+        if !job.description.snapshot_iterations.is_empty()
+            && instance.display_coordinates().is_none()
+        {
+            panic!(
+                "run description {} asks for snapshots, but {} has no coordinates to draw its cities at",
+                job.index, data_file
+            );
+        }
+    }
+
+    info!("---- starting {} runs", jobs.len());
+    let manifest = Mutex::new(manifest);
+    if num_jobs == 1 {
+        for job in jobs.iter() {
+            let instance = &instances[job.description.data_file.as_str()];
+            execute_job(job, instance, &options, &manifest);
+        }
+    } else {
+        // each worker takes the next job that hasn't been started yet
+        let next_job = AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for worker in 1..=num_jobs.min(jobs.len()) {
+                // named so log messages show which worker (and so which run) they came from
+                std::thread::Builder::new()
+                    .name(format!("worker-{}", worker))
+                    .spawn_scoped(scope, || loop {
+                        let i = next_job.fetch_add(1, Ordering::SeqCst);
+                        let job = match jobs.get(i) {
+                            Some(job) => job,
+                            None => break,
+                        };
+                        let instance = &instances[job.description.data_file.as_str()];
+                        execute_job(job, instance, &options, &manifest);
+                    })
+                    .expect("failed spawning worker thread");
+            }
+        });
+    }
+    info!("execution finished");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn description(out_path: &str, algorithm: &str) -> RunDescription {
+        serde_json::from_str(&format!(
+            r#"{{"data_file": "a.tsp", "out_path": "{}", "parameters": {{"algorithm": "{}"}}}}"#,
+            out_path, algorithm
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn only_later_descriptions_with_the_same_out_file_get_their_index() {
+        let descriptions = vec![
+            description("results/a280", "mmas"),
+            description("results/a280", "acs"),
+            description("results/a280", "mmas"),
+            description("results/rat783", "mmas"),
+        ];
+        let paths: Vec<PathBuf> = (0..descriptions.len())
+            .map(|index| {
+                let shares = shares_out_file(&descriptions, index);
+                out_file_path(&descriptions[index], index, 3, shares)
+            })
+            .collect();
+        let expected = [
+            "results/a280/results_a280_Mmas_3.txt",
+            "results/a280/results_a280_Acs_3.txt",
+            "results/a280/results_a280_Mmas_d2_3.txt",
+            "results/rat783/results_rat783_Mmas_3.txt",
+        ];
+        for (path, expected) in paths.iter().zip(expected.iter()) {
+            assert_eq!(path, &PathBuf::from(expected));
+        }
     }
-    println!("execution finished");
 }
@@ -0,0 +1,176 @@
+use parallelized_acotsp::parameters_reader::RunDescription;
+use serde_derive::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// A run that finished and had its results written.
+#[derive(Serialize, Deserialize, PartialEq)]
+struct CompletedRun {
+    /// Index of the run description in the run file
+    description: usize,
+    /// `content_hash` of the run description. Missing from manifests written
+    /// before it was stored, whose runs are matched by index alone.
+    #[serde(default)]
+    description_hash: Option<u64>,
+    run: usize,
+    /// Path of the result file the run wrote
+    out_file: PathBuf,
+}
+
+impl CompletedRun {
+    fn new(index: usize, description: &RunDescription, run: usize, out_file: &Path) -> Self {
+        CompletedRun {
+            description: index,
+            description_hash: Some(description.content_hash),
+            run,
+            out_file: out_file.to_owned(),
+        }
+    }
+
+    /// Whether this is a completion of the same run. Once the hash is known, the
+    /// description may have moved to another index in the run file, but runs of
+    /// a description whose contents changed don't match.
+    fn matches(&self, other: &CompletedRun) -> bool {
+        let same_description = match self.description_hash {
+            Some(hash) => other.description_hash == Some(hash),
+            None => self.description == other.description,
+        };
+        same_description && self.run == other.run && self.out_file == other.out_file
+    }
+}
+
+/// Record of which runs of a run file have been completed, stored next to the
+/// run file so an interrupted batch can be started again without repeating
+/// (and overwriting) finished runs.
+pub struct Manifest {
+    path: PathBuf,
+    completed: Vec<CompletedRun>,
+}
+
+impl Manifest {
+    /// Loads the manifest for the given run file, or creates an empty one if
+    /// none exists yet.
+    pub fn load_or_new(run_file_name: &str) -> Manifest {
+        let path = PathBuf::from(format!("{}.manifest", run_file_name));
+        let completed = if path.exists() {
+            let f = File::open(&path).expect("could not open batch manifest");
+            serde_json::from_reader(BufReader::new(f)).expect("could not read batch manifest")
+        } else {
+            Vec::new()
+        };
+        Manifest { path, completed }
+    }
+
+    /// Forgets all completed runs, so the whole batch is executed again.
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.completed.clear();
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+
+    /// Whether a run has been completed. Runs whose result file no longer exists
+    /// are not considered complete.
+    pub fn is_completed(
+        &self,
+        index: usize,
+        description: &RunDescription,
+        run: usize,
+        out_file: &Path,
+    ) -> bool {
+        let entry = CompletedRun::new(index, description, run, out_file);
+        self.completed.iter().any(|c| c.matches(&entry)) && out_file.exists()
+    }
+
+    /// Records a run as completed and saves the manifest.
+    pub fn mark_completed(
+        &mut self,
+        index: usize,
+        description: &RunDescription,
+        run: usize,
+        out_file: &Path,
+    ) -> io::Result<()> {
+        let entry = CompletedRun::new(index, description, run, out_file);
+        if !self.completed.contains(&entry) {
+            self.completed.push(entry);
+        }
+        self.save()
+    }
+
+    /// Writes to a temporary file and renames it so an interruption can't leave
+    /// a half-written manifest behind.
+    fn save(&self) -> io::Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            serde_json::to_writer_pretty(&mut writer, &self.completed)?;
+            writer.flush()?;
+        }
+        fs::rename(tmp_path, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::test_dir;
+    use parallelized_acotsp::parameters_reader::read_run_file;
+
+    #[test]
+    fn completed_runs_are_skipped_until_their_description_changes() {
+        let dir = test_dir("manifest");
+        let run_file = dir.join("runs.json");
+        let run_file_name = run_file.to_str().unwrap();
+        let write_run_file = |descriptions: &str| fs::write(&run_file, descriptions).unwrap();
+        let mmas =
+            r#"{"data_file": "a.tsp", "out_path": "out", "parameters": {"algorithm": "mmas"}}"#;
+        let acs =
+            r#"{"data_file": "a.tsp", "out_path": "out", "parameters": {"algorithm": "acs"}}"#;
+        let out_file = dir.join("result.txt");
+        fs::write(&out_file, "").unwrap();
+
+        write_run_file(&format!("[{}, {}]", mmas, acs));
+        let descriptions = read_run_file(run_file_name);
+        let mut manifest = Manifest::load_or_new(run_file_name);
+        assert!(!manifest.is_completed(0, &descriptions[0], 1, &out_file));
+        manifest
+            .mark_completed(0, &descriptions[0], 1, &out_file)
+            .unwrap();
+
+        // resuming reads the manifest back
+        let manifest = Manifest::load_or_new(run_file_name);
+        assert!(manifest.is_completed(0, &descriptions[0], 1, &out_file));
+        assert!(!manifest.is_completed(0, &descriptions[0], 2, &out_file));
+        assert!(!manifest.is_completed(1, &descriptions[1], 1, &out_file));
+
+        // reordering the run file keeps the completion with its description
+        write_run_file(&format!("[{}, {}]", acs, mmas));
+        let descriptions = read_run_file(run_file_name);
+        assert!(!manifest.is_completed(0, &descriptions[0], 1, &out_file));
+        assert!(manifest.is_completed(1, &descriptions[1], 1, &out_file));
+
+        // more runs of the same description don't invalidate the finished ones
+        write_run_file(&format!(
+            "[{}, {}]",
+            acs,
+            mmas.replacen('{', r#"{"num_runs": 5, "#, 1)
+        ));
+        let descriptions = read_run_file(run_file_name);
+        assert_eq!(descriptions[1].num_runs, 5);
+        assert!(manifest.is_completed(1, &descriptions[1], 1, &out_file));
+
+        // editing it does
+        write_run_file(&format!("[{}, {}]", acs, mmas.replace("a.tsp", "b.tsp")));
+        let descriptions = read_run_file(run_file_name);
+        assert!(!manifest.is_completed(1, &descriptions[1], 1, &out_file));
+
+        // so does losing the result file
+        write_run_file(&format!("[{}, {}]", acs, mmas));
+        let descriptions = read_run_file(run_file_name);
+        fs::remove_file(&out_file).unwrap();
+        assert!(!manifest.is_completed(1, &descriptions[1], 1, &out_file));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,7 @@
 use crate::aco::AcoParameters;
+use crate::instance_reader::InstanceFormat;
 use serde_derive::Deserialize;
+use serde_json::Value;
 use std::fs::read_to_string;
 
 #[derive(Deserialize)]
@@ -7,13 +9,71 @@ pub struct RunDescription {
     #[serde(default = "default_num_runs")]
     pub num_runs: usize,
     pub data_file: String,
+    /// Format of the data file: "tsplib", "csv", "json" or "matrix". Chosen by
+    /// the file's extension if not given.
+    #[serde(default)]
+    pub format: Option<InstanceFormat>,
     pub out_path: String,
+    /// Interval in seconds between checkpoints of each run. No checkpoints
+    /// are saved if not given.
+    #[serde(default)]
+    pub checkpoint_interval: Option<u64>,
+    /// Length of an optimal tour of the instance, if known. Used to show the
+    /// gap of the best tour found in the progress display and the results.
+    #[serde(default)]
+    pub optimum: Option<u32>,
+    /// Rewrite the run's `.tour` file every time a new best tour is found, so
+    /// that the best tour so far is always on disk. Otherwise it is only written
+    /// once the run is over.
+    #[serde(default)]
+    pub rewrite_best_tour: bool,
+    /// TSPLIB `.tour` file with an optimal tour of the instance, whose length is
+    /// used as the optimum if `optimum` is not given.
+    #[serde(default)]
+    pub optimal_tour: Option<String>,
+    /// TSPLIB `.tour` file with a tour for the colony to start from.
+    #[serde(default)]
+    pub initial_tour: Option<String>,
+    /// Iterations after which an SVG image of the best tour so far and the
+    /// pheromones is written next to the results of each run.
+    #[serde(default)]
+    pub snapshot_iterations: Vec<usize>,
     pub parameters: AcoParameters,
+    /// Hash of everything in the description except `num_runs`, so that runs
+    /// recorded as completed can be told apart from runs of a description that
+    /// was edited since. Key order and formatting don't change it.
+    #[serde(skip)]
+    pub content_hash: u64,
 }
 
 pub fn read_run_file(f_name: &str) -> Vec<RunDescription> {
     let contents = read_to_string(f_name).expect("could not read run file");
-    serde_json::from_str(&contents).expect("Could not convert paremeters from JSON")
+    let values: Vec<Value> =
+        serde_json::from_str(&contents).expect("Could not convert paremeters from JSON");
+    values
+        .into_iter()
+        .map(|value| {
+            let mut contents = value.clone();
+            if let Value::Object(fields) = &mut contents {
+                fields.remove("num_runs");
+            }
+            // objects keep their keys sorted, so the same description always
+            // prints the same way
+            let content_hash = fnv1a(contents.to_string().as_bytes());
+            let mut description: RunDescription =
+                serde_json::from_value(value).expect("Could not convert paremeters from JSON");
+            description.content_hash = content_hash;
+            description
+        })
+        .collect()
+}
+
+/// 64 bit FNV-1a hash, which unlike the standard library's hasher is the same on
+/// every platform and version of Rust.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
 }
 
 fn default_num_runs() -> usize {
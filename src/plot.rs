@@ -0,0 +1,147 @@
+use crate::validate::read_tour_or_result;
+use crate::{format_arg, instance_format};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use log::warn;
+use parallelized_acotsp::aco::{Ant, Checkpoint, ColonyState, Control, Observer};
+use parallelized_acotsp::instance_reader::read_instance_file_as;
+use parallelized_acotsp::svg::{plot_svg, PlotOptions};
+use std::fs::{self, read_to_string};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("plot")
+        .about("Draws the cities of an instance and a tour as an SVG image")
+        .arg(
+            Arg::with_name("INSTANCE")
+                .help("File of the instance")
+                .required(true),
+        )
+        .arg(format_arg())
+        .arg(Arg::with_name("TOUR").help(
+            "TSPLIB .tour file, or result file written by a run. Defaults to the best tour of the checkpoint, if given",
+        ))
+        .arg(
+            Arg::with_name("Output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("SVG file to write (default: the tour or instance file with an .svg extension)"),
+        )
+        .arg(
+            Arg::with_name("Checkpoint")
+                .long("checkpoint")
+                .takes_value(true)
+                .help("Checkpoint of a run whose pheromones are drawn under the tour"),
+        )
+        .arg(
+            Arg::with_name("Pheromone Edges")
+                .long("pheromone-edges")
+                .takes_value(true)
+                .help("Number of edges with the most pheromone drawn for each city (default 5)"),
+        )
+        .arg(
+            Arg::with_name("Width")
+                .long("width")
+                .takes_value(true)
+                .help("Width of the image in pixels (default 800)"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) {
+    let instance_file = matches
+        .value_of("INSTANCE")
+        .expect("failed parsing argument");
+    let instance = read_instance_file_as(instance_file, instance_format(matches));
+    let coordinates = instance
+        .display_coordinates()
+        .expect("instance has no coordinates to draw its cities at");
+    let checkpoint = matches
+        .value_of("Checkpoint")
+        .map(|f| Checkpoint::load(Path::new(f)).expect("failed reading checkpoint"));
+    let tour = match matches.value_of("TOUR") {
+        Some(tour_file) => {
+            let contents = read_to_string(tour_file).expect("Failed opening tour file");
+            let (tour_file, _) = read_tour_or_result(&contents);
+            let ant = tour_file.to_ant(&instance.data).expect("invalid tour");
+            Some(ant)
+        }
+        None => checkpoint
+            .as_ref()
+            .filter(|c| !c.result_log.log.is_empty())
+            .map(|c| c.result_log.best_tour().clone()),
+    };
+    let pheromones = checkpoint
+        .as_ref()
+        .map(|c| c.colony.pheromones().expect("checkpoint has no pheromones"));
+    let mut options = PlotOptions::default();
+    if let Some(k) = matches.value_of("Pheromone Edges") {
+        options.pheromone_edges = k.parse().expect("invalid number of pheromone edges");
+    }
+    if let Some(width) = matches.value_of("Width") {
+        options.width = width.parse().expect("invalid width");
+    }
+    options.title = Some(match &tour {
+        Some(ant) => format!("{}, length {}", instance.metadata.name, ant.length),
+        None => instance.metadata.name.clone(),
+    });
+    let output = match matches.value_of("Output") {
+        Some(output) => PathBuf::from(output),
+        None => Path::new(matches.value_of("TOUR").unwrap_or(instance_file)).with_extension("svg"),
+    };
+    let tour = tour.as_ref().map(|ant| &ant.tour[..]);
+    let svg = plot_svg(&coordinates, tour, pheromones, &options);
+    fs::write(&output, svg).expect("failed writing SVG file");
+    println!("wrote {}", output.display());
+}
+
+/// Draws the best tour so far and the pheromones of a run after each of the
+/// given iterations.
+pub struct SnapshotPlotter {
+    pub coordinates: Vec<(f64, f64)>,
+    pub iterations: Vec<usize>,
+    /// Snapshot files are named after this, with the iteration added
+    pub path: PathBuf,
+    pub label: String,
+    pub best: Option<Ant>,
+}
+
+impl Observer for SnapshotPlotter {
+    fn on_new_best(&mut self, _: usize, best: &Ant, _: Duration) -> Control {
+        self.best = Some(best.clone());
+        Control::Continue
+    }
+
+    fn on_pheromones_updated(
+        &mut self,
+        iteration: usize,
+        state: &dyn Fn() -> ColonyState,
+    ) -> Control {
+        if !self.iterations.contains(&iteration) {
+            return Control::Continue;
+        }
+        let state = state();
+        let best = self.best.as_ref();
+        let options = PlotOptions {
+            title: Some(format!(
+                "{} iteration {}, best {}",
+                self.label,
+                iteration,
+                best.map_or(0, |ant| ant.length)
+            )),
+            ..PlotOptions::default()
+        };
+        let svg = plot_svg(
+            &self.coordinates,
+            best.map(|ant| &ant.tour[..]),
+            state.pheromones(),
+            &options,
+        );
+        let path = self.path.with_extension(format!("iter{}.svg", iteration));
+        // a missing snapshot isn't worth losing the run over
+        if let Err(e) = fs::write(&path, svg) {
+            warn!("{} failed writing {}: {}", self.label, path.display(), e);
+        }
+        Control::Continue
+    }
+}
@@ -0,0 +1,238 @@
+use parking_lot::Mutex;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Latest state of a single run, as reported by `run_colony`.
+struct RunStatus {
+    label: String,
+    optimum: Option<u32>,
+    time_limit: usize,
+    max_iterations: usize,
+    iteration: usize,
+    best_length: Option<u32>,
+    restarts: usize,
+    elapsed: Duration,
+    finished: bool,
+}
+
+/// Handle through which a running colony reports its progress. Updating it only
+/// takes a lock once per iteration, so it has no effect on solution construction.
+#[derive(Clone)]
+pub struct RunProgress(Arc<Mutex<RunStatus>>);
+
+impl RunProgress {
+    pub fn update(&self, iteration: usize, best_length: u32, restarts: usize, elapsed: Duration) {
+        let mut status = self.0.lock();
+        status.iteration = iteration;
+        status.best_length = Some(best_length);
+        status.restarts = restarts;
+        status.elapsed = elapsed;
+    }
+
+    /// Removes the run from the display the next time it is redrawn.
+    pub fn finish(&self) {
+        self.0.lock().finished = true;
+    }
+}
+
+/// Iteration count of a run the last time it was drawn, used to calculate
+/// iterations per second over the last refresh interval.
+struct Sample {
+    iteration: usize,
+    taken_at: Instant,
+    rate: f64,
+}
+
+/// A run shown by the display, until it finishes.
+struct DisplayedRun {
+    progress: RunProgress,
+    /// Taken the first time the run is drawn
+    sample: Option<Sample>,
+}
+
+/// Status lines on stderr for every run currently executing, redrawn
+/// periodically by a background thread.
+pub struct ProgressDisplay {
+    runs: Arc<Mutex<Vec<DisplayedRun>>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ProgressDisplay {
+    pub fn start() -> ProgressDisplay {
+        let runs: Arc<Mutex<Vec<DisplayedRun>>> = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let runs = Arc::clone(&runs);
+            let stop = Arc::clone(&stop);
+            // the display is only a convenience, so failing to write it (e.g. to a
+            // closed pipe) is ignored rather than taking the runs down with it
+            thread::spawn(move || {
+                let mut lines_drawn = 0;
+                while !stop.load(Ordering::Relaxed) {
+                    let stderr = io::stderr();
+                    lines_drawn =
+                        redraw(&mut stderr.lock(), &mut runs.lock(), lines_drawn).unwrap_or(0);
+                    thread::sleep(REFRESH_INTERVAL);
+                }
+                let _ = clear_lines(&mut io::stderr(), lines_drawn);
+            })
+        };
+        ProgressDisplay {
+            runs,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Adds a run to the display and returns the handle it should report to.
+    pub fn add_run(
+        &self,
+        label: String,
+        optimum: Option<u32>,
+        time_limit: usize,
+        max_iterations: usize,
+    ) -> RunProgress {
+        let progress = RunProgress(Arc::new(Mutex::new(RunStatus {
+            label,
+            optimum,
+            time_limit,
+            max_iterations,
+            iteration: 0,
+            best_length: None,
+            restarts: 0,
+            elapsed: Duration::from_secs(0),
+            finished: false,
+        })));
+        self.runs.lock().push(DisplayedRun {
+            progress: progress.clone(),
+            sample: None,
+        });
+        progress
+    }
+}
+
+impl Drop for ProgressDisplay {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn clear_lines(out: &mut impl Write, lines: usize) -> io::Result<()> {
+    for _ in 0..lines {
+        // move up a line and erase it
+        write!(out, "\x1b[1A\x1b[2K")?;
+    }
+    Ok(())
+}
+
+/// Replaces the previously drawn lines with the current status of every
+/// unfinished run, dropping the runs that finished. Returns how many lines were
+/// drawn.
+fn redraw(
+    out: &mut impl Write,
+    runs: &mut Vec<DisplayedRun>,
+    lines_drawn: usize,
+) -> io::Result<usize> {
+    runs.retain(|run| !run.progress.0.lock().finished);
+    clear_lines(out, lines_drawn)?;
+    let now = Instant::now();
+    for run in runs.iter_mut() {
+        let status = run.progress.0.lock();
+        let sample = run.sample.get_or_insert(Sample {
+            iteration: status.iteration,
+            taken_at: now,
+            rate: 0.0,
+        });
+        let secs = (now - sample.taken_at).as_secs_f64();
+        if secs > 0.0 {
+            sample.rate = (status.iteration - sample.iteration) as f64 / secs;
+            sample.iteration = status.iteration;
+            sample.taken_at = now;
+        }
+        writeln!(out, "{}", status_line(&status, sample.rate))?;
+    }
+    out.flush()?;
+    Ok(runs.len())
+}
+
+fn status_line(status: &RunStatus, rate: f64) -> String {
+    let best = match status.best_length {
+        Some(best) => best.to_string(),
+        None => "-".to_owned(),
+    };
+    let gap = match (status.best_length, status.optimum) {
+        (Some(best), Some(optimum)) => format!(
+            " gap {:.2}%",
+            100.0 * (best as f64 - optimum as f64) / optimum as f64
+        ),
+        _ => String::new(),
+    };
+    format!(
+        "{}: iter {} ({:.1} it/s) best {}{} restarts {} remaining {}",
+        status.label,
+        status.iteration,
+        rate,
+        best,
+        gap,
+        status.restarts,
+        remaining(status, rate)
+    )
+}
+
+/// Estimates the remaining time from whichever limit will be reached first.
+fn remaining(status: &RunStatus, rate: f64) -> String {
+    let by_time = (status.time_limit as f64 - status.elapsed.as_secs_f64()).max(0.0);
+    let by_iterations = if rate > 0.0 {
+        status.max_iterations.saturating_sub(status.iteration) as f64 / rate
+    } else {
+        f64::INFINITY
+    };
+    let secs = by_time.min(by_iterations);
+    if secs.is_finite() && secs < 1e9 {
+        format!("{}s", secs.round())
+    } else {
+        "?".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finished_runs_are_removed_from_the_display() {
+        let display = ProgressDisplay {
+            runs: Arc::new(Mutex::new(Vec::new())),
+            stop: Arc::new(AtomicBool::new(true)),
+            thread: None,
+        };
+        let first = display.add_run("first".to_owned(), Some(100), 60, 1000);
+        let second = display.add_run("second".to_owned(), None, 60, 1000);
+        first.update(10, 110, 0, Duration::from_secs(1));
+        second.finish();
+
+        let mut out = Vec::new();
+        let lines = redraw(&mut out, &mut display.runs.lock(), 0).unwrap();
+        assert_eq!(lines, 1);
+        assert_eq!(display.runs.lock().len(), 1);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("first: iter 10 "), "{}", out);
+        assert!(out.contains("best 110 gap 10.00%"), "{}", out);
+
+        first.finish();
+        let mut out = Vec::new();
+        assert_eq!(redraw(&mut out, &mut display.runs.lock(), 1).unwrap(), 0);
+        assert!(display.runs.lock().is_empty());
+        // only the line drawn before is erased
+        assert_eq!(out, b"\x1b[1A\x1b[2K");
+    }
+}
@@ -0,0 +1,284 @@
+use crate::manifest::Manifest;
+use crate::{out_file_path, shares_out_file};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use parallelized_acotsp::parameters_reader::{read_run_file, RunDescription};
+use parallelized_acotsp::result_file::{read_result_file, RunResult};
+use parallelized_acotsp::stats::{mean, mean_confidence_interval, std_dev};
+use parallelized_acotsp::svg::{escape, line_chart, ChartOptions, Series};
+use std::fmt::Write;
+use std::fs;
+use std::time::Duration;
+
+/// Most points drawn for each line of the convergence charts.
+const CHART_POINTS: usize = 200;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("report")
+        .about(
+            "Summarises the completed runs of a run file as an HTML page with convergence charts",
+        )
+        .arg(
+            Arg::with_name("RUN DESCRIPTION FILE")
+                .help("JSON run file whose runs are summarised")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("Output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("HTML file to write (default: the run file with a .report.html extension)"),
+        )
+}
+
+/// Results of all completed runs of one algorithm on an instance.
+pub struct Group {
+    pub label: String,
+    pub runs: Vec<RunResult>,
+}
+
+/// Results of the completed runs on one instance, grouped by algorithm.
+pub struct InstanceResults {
+    pub data_file: String,
+    pub groups: Vec<Group>,
+}
+
+pub fn run(matches: &ArgMatches) {
+    let run_file_name = matches
+        .value_of("RUN DESCRIPTION FILE")
+        .expect("failed parsing argument");
+    let instances = completed_runs(run_file_name);
+    let out_file = matches
+        .value_of("Output")
+        .map(str::to_owned)
+        .unwrap_or_else(|| format!("{}.report.html", run_file_name));
+    fs::write(&out_file, report_html(run_file_name, &instances)).expect("failed writing report");
+    println!("report written to {}", out_file);
+}
+
+/// Reads the results of the runs of a run file recorded as completed in its
+/// manifest. Instances are in the order they first appear in the run file.
+pub fn completed_runs(run_file_name: &str) -> Vec<InstanceResults> {
+    let descriptions = read_run_file(run_file_name);
+    let manifest = Manifest::load_or_new(run_file_name);
+    let mut instances: Vec<InstanceResults> = Vec::new();
+    for (index, description) in descriptions.iter().enumerate() {
+        let shares_out_file = shares_out_file(&descriptions, index);
+        let mut runs = Vec::new();
+        for run in 1..=description.num_runs {
+            let out_file = out_file_path(description, index, run, shares_out_file);
+            if !manifest.is_completed(index, description, run, &out_file) {
+                continue;
+            }
+            match read_result_file(&out_file) {
+                Ok(result) => runs.push(result),
+                Err(e) => eprintln!("skipping run {} of description {}: {}", run, index, e),
+            }
+        }
+        if runs.is_empty() {
+            continue;
+        }
+        let group = Group {
+            label: group_label(&descriptions, index),
+            runs,
+        };
+        match instances
+            .iter_mut()
+            .find(|i| i.data_file == description.data_file)
+        {
+            Some(instance) => instance.groups.push(group),
+            None => instances.push(InstanceResults {
+                data_file: description.data_file.clone(),
+                groups: vec![group],
+            }),
+        }
+    }
+    instances
+}
+
+/// Name of the algorithm of a description, with the description's index if the
+/// same algorithm is run on the instance by other descriptions too.
+fn group_label(descriptions: &[RunDescription], index: usize) -> String {
+    let description = &descriptions[index];
+    let repeated = descriptions.iter().enumerate().any(|(other, d)| {
+        other != index
+            && d.data_file == description.data_file
+            && d.parameters.algorithm == description.parameters.algorithm
+    });
+    if repeated {
+        format!("{} #{}", description.parameters.algorithm, index)
+    } else {
+        description.parameters.algorithm.to_string()
+    }
+}
+
+fn report_html(run_file_name: &str, instances: &[InstanceResults]) -> String {
+    let title = format!("Report of {}", run_file_name);
+    let mut html = html_start(&title);
+    if instances.is_empty() {
+        let _ = writeln!(html, "<p>No runs have been completed yet.</p>");
+    }
+    for InstanceResults { data_file, groups } in instances {
+        let _ = writeln!(html, "<h2>{}</h2>", escape(data_file));
+        html.push_str(&summary_table(groups));
+        html.push_str(&line_chart(
+            &groups.iter().map(iteration_series).collect::<Vec<_>>(),
+            &ChartOptions {
+                title: "Best tour by iteration (mean and 95% CI)".to_owned(),
+                x_label: "iteration".to_owned(),
+                y_label: "tour length".to_owned(),
+                ..ChartOptions::default()
+            },
+        ));
+        html.push_str(&line_chart(
+            &groups.iter().map(time_series).collect::<Vec<_>>(),
+            &ChartOptions {
+                title: "Best tour by time (mean and 95% CI)".to_owned(),
+                x_label: "time (s)".to_owned(),
+                y_label: "tour length".to_owned(),
+                ..ChartOptions::default()
+            },
+        ));
+    }
+    let _ = writeln!(html, "</body>\n</html>");
+    html
+}
+
+/// Start of a self-contained HTML page, up to its heading.
+pub fn html_start(title: &str) -> String {
+    let mut html = String::new();
+    let _ = writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>",
+        escape(title)
+    );
+    let _ = writeln!(
+        html,
+        "<style>\nbody {{ font-family: sans-serif; margin: 2em; }}\n\
+         table {{ border-collapse: collapse; margin-bottom: 1em; }}\n\
+         th, td {{ border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: right; }}\n\
+         th:first-child, td:first-child {{ text-align: left; }}\n\
+         svg {{ margin-right: 1em; }}\n</style>\n</head>\n<body>"
+    );
+    let _ = writeln!(html, "<h1>{}</h1>", escape(title));
+    html
+}
+
+fn summary_table(groups: &[Group]) -> String {
+    let mut html = String::new();
+    let _ = writeln!(
+        html,
+        "<table>\n<tr><th>algorithm</th><th>runs</th><th>best</th><th>mean &plusmn; sd</th>\
+         <th>worst</th><th>mean gap</th><th>mean time to best (s)</th><th>mean iterations</th></tr>"
+    );
+    for group in groups {
+        let lengths: Vec<f64> = group.runs.iter().map(|r| r.best_length as f64).collect();
+        let gaps: Vec<f64> = group
+            .runs
+            .iter()
+            .filter_map(|r| {
+                r.optimum
+                    .map(|o| (r.best_length as f64 - o as f64) / o as f64)
+            })
+            .collect();
+        let gap = if gaps.len() == group.runs.len() {
+            format!("{:.2}%", 100.0 * mean(&gaps))
+        } else {
+            "-".to_owned()
+        };
+        let times: Vec<f64> = group
+            .runs
+            .iter()
+            .map(|r| r.best_timestamp.as_secs_f64())
+            .collect();
+        let iterations: Vec<f64> = group
+            .runs
+            .iter()
+            .map(|r| r.iterations.len() as f64)
+            .collect();
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.1} &plusmn; {:.1}</td><td>{}</td>\
+             <td>{}</td><td>{:.3}</td><td>{:.1}</td></tr>",
+            escape(&group.label),
+            group.runs.len(),
+            group.runs.iter().map(|r| r.best_length).min().unwrap(),
+            mean(&lengths),
+            std_dev(&lengths),
+            group.runs.iter().map(|r| r.best_length).max().unwrap(),
+            gap,
+            mean(&times),
+            mean(&iterations)
+        );
+    }
+    let _ = writeln!(html, "</table>");
+    html
+}
+
+/// Mean length of the best tour found by each iteration over the runs of a
+/// group. Runs that ended earlier than others keep their final best tour.
+fn iteration_series(group: &Group) -> Series {
+    let best_so_far: Vec<Vec<u32>> = group.runs.iter().map(RunResult::best_so_far).collect();
+    let num_iterations = best_so_far.iter().map(Vec::len).max().unwrap_or(0);
+    let step = num_iterations.div_ceil(CHART_POINTS).max(1);
+    let mut iterations: Vec<usize> = (0..num_iterations).step_by(step).collect();
+    if num_iterations > 0 && iterations.last() != Some(&(num_iterations - 1)) {
+        iterations.push(num_iterations - 1);
+    }
+    let values = iterations.iter().map(|&i| {
+        let lengths: Vec<f64> = best_so_far
+            .iter()
+            .filter_map(|b| b.get(i).or_else(|| b.last()))
+            .map(|&l| l as f64)
+            .collect();
+        ((i + 1) as f64, lengths)
+    });
+    series(&group.label, values)
+}
+
+/// Mean length of the best tour found by each point in time over the runs of a
+/// group, from the time every run had found a tour.
+fn time_series(group: &Group) -> Series {
+    let first = |r: &RunResult| r.iterations.first().map_or(Duration::ZERO, |i| i.timestamp);
+    let last = |r: &RunResult| r.iterations.last().map_or(Duration::ZERO, |i| i.timestamp);
+    let start = group.runs.iter().map(first).max().unwrap_or_default();
+    let end = group.runs.iter().map(last).max().unwrap_or_default();
+    let num_points = if end > start { CHART_POINTS / 2 } else { 1 };
+    let values = (0..num_points).map(|p| {
+        let time = if num_points == 1 {
+            start
+        } else {
+            start + (end - start).mul_f64(p as f64 / (num_points - 1) as f64)
+        };
+        let lengths: Vec<f64> = group
+            .runs
+            .iter()
+            .filter_map(|r| r.best_at(time))
+            .map(|l| l as f64)
+            .collect();
+        (time.as_secs_f64(), lengths)
+    });
+    series(&group.label, values)
+}
+
+fn series(label: &str, values: impl Iterator<Item = (f64, Vec<f64>)>) -> Series {
+    let mut series = Series {
+        label: label.to_owned(),
+        ..Series::default()
+    };
+    for (x, lengths) in values {
+        let (low, high) = mean_confidence_interval(&lengths);
+        series.points.push((x, mean(&lengths)));
+        series.band.push((x, low, high));
+    }
+    series
+}
+
+/// Quotes a CSV field if it contains a separator or a quote.
+pub fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
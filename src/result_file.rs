@@ -0,0 +1,262 @@
+//! The text files the results of each run are written to, and reading them back
+//! to analyse a batch of runs.
+
+use crate::aco::{Phase, ResultLog};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+pub fn write_result_file(results: &ResultLog, out: &Path, optimum: Option<u32>) -> io::Result<()> {
+    if let Some(out_path) = out.parent() {
+        fs::create_dir_all(out_path)?;
+    }
+    let f = File::create(out)?;
+    let mut writer = BufWriter::new(f);
+    let best = results.best_timestamped();
+    writeln!(writer, "BEST FOUND: {}", results.best_length())?;
+    if let Some(optimum) = optimum {
+        writeln!(writer, "OPTIMUM: {}", optimum)?;
+        writeln!(
+            writer,
+            "GAP: {:.2}%",
+            100.0 * (results.best_length() as f64 - optimum as f64) / optimum as f64
+        )?;
+    }
+    writeln!(writer, "SEED: {}", results.seed)?;
+    writeln!(
+        writer,
+        "BEST TOUR: {:?}",
+        results
+            .best_tour()
+            .tour
+            .iter()
+            .map(|i| i + 1)
+            .collect::<Vec<u32>>()
+    )?;
+
+    writeln!(
+        writer,
+        "Found on iteration {} at {}.{:03}s",
+        best.iteration,
+        best.timestamp.as_secs(),
+        best.timestamp.subsec_millis()
+    )?;
+    if results.phase_times.is_enabled() {
+        writeln!(writer, "PHASE TIMES (total, calls, mean per call):")?;
+        for &phase in Phase::ALL.iter() {
+            let total = results.phase_times.total(phase);
+            let calls = results.phase_times.calls(phase);
+            let mean = if calls > 0 { total / calls } else { total };
+            writeln!(
+                writer,
+                "{:?}: {}.{:03}s, {}, {}us",
+                phase,
+                total.as_secs(),
+                total.subsec_millis(),
+                calls,
+                mean.as_micros()
+            )?;
+        }
+    }
+    writeln!(writer, "==========================")?;
+    for (i, t) in results.log.iter().enumerate() {
+        writeln!(writer, "-----Iter {}, new_best: {}", i + 1, t.is_new_best)?;
+        writeln!(
+            writer,
+            "length: {} time {}.{:03}s",
+            t.length,
+            t.timestamp.as_secs(),
+            t.timestamp.subsec_millis()
+        )?;
+    }
+    writer.flush()
+}
+
+/// Best tour of one iteration of a run, as written to its result file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IterationResult {
+    pub length: u32,
+    /// Time since the start of the run
+    pub timestamp: Duration,
+    pub is_new_best: bool,
+}
+
+/// The results of a run, read back from its result file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunResult {
+    pub best_length: u32,
+    pub optimum: Option<u32>,
+    pub seed: u64,
+    /// Best tour of the run, with cities numbered from 0
+    pub best_tour: Vec<usize>,
+    pub best_iteration: usize,
+    pub best_timestamp: Duration,
+    /// Results of every iteration, in order
+    pub iterations: Vec<IterationResult>,
+}
+
+impl RunResult {
+    /// Length of the best tour found up to each iteration.
+    pub fn best_so_far(&self) -> Vec<u32> {
+        self.iterations
+            .iter()
+            .scan(u32::MAX, |best, it| {
+                *best = (*best).min(it.length);
+                Some(*best)
+            })
+            .collect()
+    }
+
+    /// Length of the best tour found by the given time, if any tour was found
+    /// by then.
+    pub fn best_at(&self, time: Duration) -> Option<u32> {
+        self.iterations
+            .iter()
+            .take_while(|it| it.timestamp <= time)
+            .map(|it| it.length)
+            .min()
+    }
+
+    /// Iteration, numbered from 1, and time at which a tour at least as good as
+    /// `target` was first found, if one was found at all.
+    pub fn time_to_target(&self, target: u32) -> Option<(usize, Duration)> {
+        self.iterations
+            .iter()
+            .position(|it| it.length <= target)
+            .map(|i| (i + 1, self.iterations[i].timestamp))
+    }
+}
+
+pub fn read_result_file(path: &Path) -> io::Result<RunResult> {
+    let contents = fs::read_to_string(path)?;
+    parse_result(&contents).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+/// Reads the results of a run in the format written by `write_result_file`.
+pub fn parse_result(contents: &str) -> Result<RunResult, String> {
+    let mut best_length = None;
+    let mut optimum = None;
+    let mut seed = None;
+    let mut best_tour = None;
+    let mut best_found = None;
+    let mut iterations = Vec::new();
+    let mut new_best = false;
+    for line in contents.lines() {
+        if let Some(value) = line.strip_prefix("BEST FOUND: ") {
+            best_length = Some(parse_number(value, "BEST FOUND")?);
+        } else if let Some(value) = line.strip_prefix("OPTIMUM: ") {
+            optimum = Some(parse_number(value, "OPTIMUM")?);
+        } else if let Some(value) = line.strip_prefix("SEED: ") {
+            seed = Some(parse_number(value, "SEED")?);
+        } else if let Some(value) = line.strip_prefix("BEST TOUR: ") {
+            best_tour = Some(parse_tour(value)?);
+        } else if let Some(value) = line.strip_prefix("Found on iteration ") {
+            let mut split = value.splitn(2, " at ");
+            let iteration = parse_number(split.next().unwrap_or(""), "best iteration")?;
+            let timestamp = parse_time(split.next().unwrap_or(""))?;
+            best_found = Some((iteration, timestamp));
+        } else if let Some(value) = line.strip_prefix("-----Iter ") {
+            new_best = value.ends_with("new_best: true");
+        } else if let Some(value) = line.strip_prefix("length: ") {
+            let mut split = value.splitn(2, " time ");
+            let length = parse_number(split.next().unwrap_or(""), "length")?;
+            let timestamp = parse_time(split.next().unwrap_or(""))?;
+            iterations.push(IterationResult {
+                length,
+                timestamp,
+                is_new_best: new_best,
+            });
+        }
+    }
+    let (best_iteration, best_timestamp) = best_found.ok_or("missing best iteration")?;
+    Ok(RunResult {
+        best_length: best_length.ok_or("missing BEST FOUND")?,
+        optimum,
+        seed: seed.ok_or("missing SEED")?,
+        best_tour: best_tour.ok_or("missing BEST TOUR")?,
+        best_iteration,
+        best_timestamp,
+        iterations,
+    })
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, what: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid {}: {}", what, value))
+}
+
+/// Reads a list of cities numbered from 1, as in `[1, 3, 2]`.
+fn parse_tour(value: &str) -> Result<Vec<usize>, String> {
+    let cities = value.trim().trim_start_matches('[').trim_end_matches(']');
+    cities
+        .split(',')
+        .map(|city| match parse_number::<usize>(city, "city")? {
+            0 => Err("cities are numbered from 1".to_owned()),
+            city => Ok(city - 1),
+        })
+        .collect()
+}
+
+/// Reads a time written as seconds and milliseconds, as in `12.034s`. The
+/// milliseconds are read as a whole number, as files written by older versions
+/// did not pad them with zeros.
+fn parse_time(value: &str) -> Result<Duration, String> {
+    let value = value.trim().trim_end_matches('s');
+    let mut split = value.splitn(2, '.');
+    let secs = parse_number(split.next().unwrap_or(""), "time")?;
+    let millis = match split.next() {
+        Some(millis) => parse_number(millis, "time")?,
+        None => 0,
+    };
+    Ok(Duration::from_secs(secs) + Duration::from_millis(millis))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::aco::Ant;
+    use crate::instance_data::InstanceData;
+
+    #[test]
+    fn result_file_is_read_back() {
+        let data = InstanceData::from_coordinates(&[(0.0, 0.0), (0.0, 3.0), (4.0, 3.0)]);
+        let mut results = ResultLog::new(10);
+        results.seed = 7;
+        results.push(
+            Ant::from_tour(&data, &[0, 1, 2]),
+            1,
+            Duration::from_millis(5),
+        );
+        results.push(
+            Ant::from_tour(&data, &[0, 2, 1]),
+            2,
+            Duration::from_millis(1020),
+        );
+        let dir = crate::test_support::test_dir("result_file");
+        let path = dir.join("result.txt");
+        write_result_file(&results, &path, Some(12)).unwrap();
+        let read = read_result_file(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(read.best_length, 12);
+        assert_eq!(read.optimum, Some(12));
+        assert_eq!(read.seed, 7);
+        assert_eq!(read.best_tour, vec![0, 1, 2]);
+        assert_eq!(read.best_iteration, 1);
+        assert_eq!(read.best_timestamp, Duration::from_millis(5));
+        assert_eq!(read.iterations.len(), 2);
+        assert_eq!(read.iterations[1].timestamp, Duration::from_millis(1020));
+        assert!(!read.iterations[1].is_new_best);
+        assert_eq!(read.best_at(Duration::from_millis(4)), None);
+        assert_eq!(read.best_so_far(), vec![12, 12]);
+        // older files did not pad the milliseconds
+        assert_eq!(parse_time("1.5s"), Ok(Duration::from_millis(1005)));
+    }
+}
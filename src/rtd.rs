@@ -0,0 +1,234 @@
+use crate::report::{completed_runs, csv_field, html_start, InstanceResults};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use parallelized_acotsp::run_time_distribution::RunTimeDistribution;
+use parallelized_acotsp::svg::{escape, line_chart, ChartOptions, Series};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::Duration;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("rtd")
+        .about("Computes run-time distributions of the completed runs of a run file for several target tour lengths")
+        .arg(
+            Arg::with_name("RUN DESCRIPTION FILE")
+                .help("JSON run file whose runs are analysed")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("Targets")
+                .long("targets")
+                .takes_value(true)
+                .help("Comma separated targets, as gaps in percent over the optimum, or over the best tour of all runs on the instance if it has no known optimum (default 5,2,1,0)"),
+        )
+        .arg(
+            Arg::with_name("Output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("Prefix of the .rtd.csv, .ttt.csv and .rtd.html files written (default: the run file)"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) {
+    let run_file_name = matches
+        .value_of("RUN DESCRIPTION FILE")
+        .expect("failed parsing argument");
+    let targets: Vec<f64> = matches
+        .value_of("Targets")
+        .unwrap_or("5,2,1,0")
+        .split(',')
+        .map(|t| t.trim().parse().expect("invalid target gap"))
+        .collect();
+    assert!(
+        targets.iter().all(|&t| t >= 0.0),
+        "target gaps must not be negative"
+    );
+    let prefix = matches.value_of("Output").unwrap_or(run_file_name);
+    let instances = completed_runs(run_file_name);
+
+    let rtd_file = format!("{}.rtd.csv", prefix);
+    write_rtd_csv(&rtd_file, &instances, &targets).expect("failed writing run-time distributions");
+    let ttt_file = format!("{}.ttt.csv", prefix);
+    write_ttt_csv(&ttt_file, &instances, &targets).expect("failed writing times to target");
+    let html_file = format!("{}.rtd.html", prefix);
+    std::fs::write(&html_file, rtd_html(run_file_name, &instances, &targets))
+        .expect("failed writing run-time distribution charts");
+    println!("written {}, {} and {}", rtd_file, ttt_file, html_file);
+}
+
+/// Length the targets of an instance are relative to: its optimum if the runs
+/// know it, and otherwise the best tour any run found.
+fn reference_length(instance: &InstanceResults) -> (u32, bool) {
+    let runs = instance.groups.iter().flat_map(|g| g.runs.iter());
+    match runs.clone().find_map(|r| r.optimum) {
+        Some(optimum) => (optimum, true),
+        None => (runs.map(|r| r.best_length).min().unwrap(), false),
+    }
+}
+
+fn target_length(reference: u32, gap: f64) -> u32 {
+    (reference as f64 * (1.0 + gap / 100.0)).floor() as u32
+}
+
+/// Time at which the last run on an instance ended.
+fn end_time(instance: &InstanceResults) -> Duration {
+    let runs = instance.groups.iter().flat_map(|g| g.runs.iter());
+    runs.filter_map(|r| r.iterations.last())
+        .map(|it| it.timestamp)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Writes the points at which the probability of having reached each target rises,
+/// for each algorithm on each instance.
+fn write_rtd_csv(
+    file_name: &str,
+    instances: &[InstanceResults],
+    targets: &[f64],
+) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(file_name)?);
+    writeln!(
+        out,
+        "instance,algorithm,target_gap,target_length,runs,time,probability"
+    )?;
+    for instance in instances {
+        let (reference, _) = reference_length(instance);
+        for &gap in targets {
+            let target = target_length(reference, gap);
+            for group in instance.groups.iter() {
+                let rtd = RunTimeDistribution::new(&group.runs, target);
+                for (i, time) in rtd.times.iter().enumerate() {
+                    writeln!(
+                        out,
+                        "{},{},{},{},{},{:.3},{:.4}",
+                        csv_field(&instance.data_file),
+                        csv_field(&group.label),
+                        gap,
+                        target,
+                        rtd.runs,
+                        time.as_secs_f64(),
+                        (i + 1) as f64 / rtd.runs as f64
+                    )?;
+                }
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Writes the iteration and time at which every run reached each target, left
+/// empty for runs that never reached it.
+fn write_ttt_csv(
+    file_name: &str,
+    instances: &[InstanceResults],
+    targets: &[f64],
+) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(file_name)?);
+    writeln!(
+        out,
+        "instance,algorithm,seed,target_gap,target_length,iteration,time"
+    )?;
+    for instance in instances {
+        let (reference, _) = reference_length(instance);
+        for &gap in targets {
+            let target = target_length(reference, gap);
+            for group in instance.groups.iter() {
+                for run in group.runs.iter() {
+                    let (iteration, time) = match run.time_to_target(target) {
+                        Some((iteration, time)) => {
+                            (iteration.to_string(), format!("{:.3}", time.as_secs_f64()))
+                        }
+                        None => (String::new(), String::new()),
+                    };
+                    writeln!(
+                        out,
+                        "{},{},{},{},{},{},{}",
+                        csv_field(&instance.data_file),
+                        csv_field(&group.label),
+                        run.seed,
+                        gap,
+                        target,
+                        iteration,
+                        time
+                    )?;
+                }
+            }
+        }
+    }
+    out.flush()
+}
+
+fn rtd_html(run_file_name: &str, instances: &[InstanceResults], targets: &[f64]) -> String {
+    let title = format!("Run-time distributions of {}", run_file_name);
+    let mut html = html_start(&title);
+    if instances.is_empty() {
+        let _ = writeln!(html, "<p>No runs have been completed yet.</p>");
+    }
+    for instance in instances {
+        let (reference, is_optimum) = reference_length(instance);
+        let end = end_time(instance);
+        let _ = writeln!(html, "<h2>{}</h2>", escape(&instance.data_file));
+        let _ = writeln!(
+            html,
+            "<p>Targets are relative to {} {}.</p>",
+            if is_optimum {
+                "the optimum"
+            } else {
+                "the best tour found,"
+            },
+            reference
+        );
+        let _ = write!(html, "<table>\n<tr><th>algorithm</th>");
+        for &gap in targets {
+            let _ = write!(
+                html,
+                "<th>{}% ({}): success</th><th>median time (s)</th>",
+                gap,
+                target_length(reference, gap)
+            );
+        }
+        let _ = writeln!(html, "</tr>");
+        for group in instance.groups.iter() {
+            let _ = write!(html, "<tr><td>{}</td>", escape(&group.label));
+            for &gap in targets {
+                let rtd = RunTimeDistribution::new(&group.runs, target_length(reference, gap));
+                let median = rtd
+                    .median()
+                    .map_or("-".to_owned(), |m| format!("{:.3}", m.as_secs_f64()));
+                let _ = write!(
+                    html,
+                    "<td>{}/{}</td><td>{}</td>",
+                    rtd.times.len(),
+                    rtd.runs,
+                    median
+                );
+            }
+            let _ = writeln!(html, "</tr>");
+        }
+        let _ = writeln!(html, "</table>");
+        for &gap in targets {
+            let target = target_length(reference, gap);
+            let series: Vec<Series> = instance
+                .groups
+                .iter()
+                .map(|group| Series {
+                    label: group.label.clone(),
+                    points: RunTimeDistribution::new(&group.runs, target).steps(end),
+                    band: Vec::new(),
+                })
+                .collect();
+            html.push_str(&line_chart(
+                &series,
+                &ChartOptions {
+                    title: format!("Reaching {}% over {} (length {})", gap, reference, target),
+                    x_label: "time (s)".to_owned(),
+                    y_label: "probability".to_owned(),
+                    ..ChartOptions::default()
+                },
+            ));
+        }
+    }
+    let _ = writeln!(html, "</body>\n</html>");
+    html
+}
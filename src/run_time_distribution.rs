@@ -0,0 +1,121 @@
+//! Run-time distributions: the probability that a run of an algorithm has found a
+//! tour at least as good as a target length by each point in time, estimated from
+//! the results of many runs.
+
+use crate::result_file::RunResult;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunTimeDistribution {
+    /// Length a tour must have at most to reach the target
+    pub target: u32,
+    /// Number of runs the distribution is estimated from
+    pub runs: usize,
+    /// Times at which the runs that reached the target did so, in increasing order
+    pub times: Vec<Duration>,
+}
+
+impl RunTimeDistribution {
+    pub fn new(results: &[RunResult], target: u32) -> RunTimeDistribution {
+        let mut times: Vec<Duration> = results
+            .iter()
+            .filter_map(|r| r.time_to_target(target))
+            .map(|(_, time)| time)
+            .collect();
+        times.sort();
+        RunTimeDistribution {
+            target,
+            runs: results.len(),
+            times,
+        }
+    }
+
+    /// Fraction of the runs that had reached the target by `time`.
+    pub fn probability_at(&self, time: Duration) -> f64 {
+        if self.runs == 0 {
+            return 0.0;
+        }
+        let reached = self.times.iter().take_while(|&&t| t <= time).count();
+        reached as f64 / self.runs as f64
+    }
+
+    /// Fraction of the runs that reached the target at all.
+    pub fn success_rate(&self) -> f64 {
+        if self.runs == 0 {
+            return 0.0;
+        }
+        self.times.len() as f64 / self.runs as f64
+    }
+
+    /// Time by which half the runs had reached the target, if they did.
+    pub fn median(&self) -> Option<Duration> {
+        // the smallest time with a probability of at least 0.5
+        let needed = self.runs.div_ceil(2).max(1);
+        self.times.get(needed - 1).copied()
+    }
+
+    /// (time in seconds, probability) corners of the distribution's step curve,
+    /// from time 0 to `end`.
+    pub fn steps(&self, end: Duration) -> Vec<(f64, f64)> {
+        let mut steps = vec![(0.0, 0.0)];
+        for (i, time) in self.times.iter().enumerate() {
+            let time = time.as_secs_f64();
+            steps.push((time, i as f64 / self.runs as f64));
+            steps.push((time, (i + 1) as f64 / self.runs as f64));
+        }
+        let last = self.success_rate();
+        steps.push((end.as_secs_f64().max(steps.last().unwrap().0), last));
+        steps
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::result_file::IterationResult;
+
+    fn run(lengths_and_millis: &[(u32, u64)]) -> RunResult {
+        let iterations = lengths_and_millis
+            .iter()
+            .map(|&(length, millis)| IterationResult {
+                length,
+                timestamp: Duration::from_millis(millis),
+                is_new_best: false,
+            })
+            .collect();
+        RunResult {
+            best_length: lengths_and_millis.iter().map(|&(l, _)| l).min().unwrap(),
+            optimum: None,
+            seed: 0,
+            best_tour: Vec::new(),
+            best_iteration: 1,
+            best_timestamp: Duration::ZERO,
+            iterations,
+        }
+    }
+
+    #[test]
+    fn distribution_counts_runs_reaching_target() {
+        let runs = [
+            run(&[(120, 10), (100, 20)]),
+            run(&[(110, 10), (105, 30), (99, 40)]),
+            run(&[(130, 10), (101, 50)]),
+        ];
+        assert_eq!(
+            runs[1].time_to_target(105),
+            Some((2, Duration::from_millis(30)))
+        );
+        let rtd = RunTimeDistribution::new(&runs, 100);
+        assert_eq!(
+            rtd.times,
+            vec![Duration::from_millis(20), Duration::from_millis(40)]
+        );
+        assert_eq!(rtd.probability_at(Duration::from_millis(30)), 1.0 / 3.0);
+        assert_eq!(rtd.success_rate(), 2.0 / 3.0);
+        assert_eq!(rtd.median(), Some(Duration::from_millis(40)));
+        assert_eq!(RunTimeDistribution::new(&runs, 99).median(), None);
+        let steps = rtd.steps(Duration::from_millis(100));
+        assert_eq!(steps.len(), 6);
+        assert_eq!(steps.last(), Some(&(0.1, 2.0 / 3.0)));
+    }
+}
@@ -0,0 +1,440 @@
+//! Statistics used to summarise the results of runs and to compare algorithms.
+
+pub fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Middle value, or the mean of the two middle values of an even number of them.
+pub fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).expect("NaN value"));
+    let middle = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
+
+/// Sample standard deviation, 0 for fewer than two values.
+pub fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = mean(values);
+    let squares: f64 = values.iter().map(|v| (v - mean).powi(2)).sum();
+    (squares / (values.len() - 1) as f64).sqrt()
+}
+
+/// 95% confidence interval of the mean, from Student's t distribution. Has no
+/// width for fewer than two values.
+pub fn mean_confidence_interval(values: &[f64]) -> (f64, f64) {
+    let mean = mean(values);
+    if values.len() < 2 {
+        return (mean, mean);
+    }
+    let half_width =
+        t_quantile_975(values.len() - 1) * std_dev(values) / (values.len() as f64).sqrt();
+    (mean - half_width, mean + half_width)
+}
+
+/// 97.5% quantile of Student's t distribution with `df` degrees of freedom.
+/// Degrees of freedom missing from the table use the closest smaller entry, which
+/// errs on the side of wider intervals.
+fn t_quantile_975(df: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    match df {
+        0 => f64::INFINITY,
+        1..=30 => TABLE[df - 1],
+        31..=39 => TABLE[29],
+        40..=59 => 2.021,
+        60..=119 => 2.000,
+        120..=999 => 1.980,
+        _ => 1.960,
+    }
+}
+
+/// Result of a two-sided hypothesis test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TestResult {
+    pub statistic: f64,
+    pub p_value: f64,
+    pub effect_size: f64,
+}
+
+/// Largest samples whose p-values are computed exactly, when they have no ties.
+/// Larger samples use the normal approximation.
+const EXACT_LIMIT: usize = 20;
+
+/// Ranks of the values from 1, with tied values getting the mean of their ranks.
+pub fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].partial_cmp(&values[b]).expect("NaN value"));
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        // positions start..end are tied, with ranks start + 1 ..= end
+        let rank = (start + 1 + end) as f64 / 2.0;
+        for &i in &order[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// Sum of t^3 - t over the groups of t tied values, used to correct variances
+/// for ties.
+fn tie_correction(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).expect("NaN value"));
+    sorted
+        .chunk_by(|a, b| a == b)
+        .map(|group| (group.len() as f64).powi(3) - group.len() as f64)
+        .sum()
+}
+
+/// Mann-Whitney U test of whether two independent samples come from the same
+/// distribution. The statistic is U of `x`, and the effect size is the
+/// Vargha-Delaney A of `x` over `y`: the probability that a value of `x` is
+/// larger than one of `y`, counting ties as half.
+pub fn mann_whitney_u(x: &[f64], y: &[f64]) -> TestResult {
+    let (m, n) = (x.len(), y.len());
+    if m == 0 || n == 0 {
+        return TestResult {
+            statistic: 0.0,
+            p_value: 1.0,
+            effect_size: 0.5,
+        };
+    }
+    let all: Vec<f64> = x.iter().chain(y.iter()).copied().collect();
+    let ranks = ranks(&all);
+    let rank_sum: f64 = ranks[..m].iter().sum();
+    let u = rank_sum - (m * (m + 1)) as f64 / 2.0;
+    let ties = tie_correction(&all);
+    let p_value = if ties == 0.0 && m <= EXACT_LIMIT && n <= EXACT_LIMIT {
+        two_sided(&mann_whitney_counts(m, n), u.round() as usize)
+    } else {
+        let total = (m + n) as f64;
+        let mean = (m * n) as f64 / 2.0;
+        let variance = (m * n) as f64 / 12.0 * ((total + 1.0) - ties / (total * (total - 1.0)));
+        normal_two_sided(u - mean, variance)
+    };
+    TestResult {
+        statistic: u,
+        p_value,
+        effect_size: u / (m * n) as f64,
+    }
+}
+
+/// Number of orderings of `m` and `n` distinct values with each value of U.
+fn mann_whitney_counts(m: usize, n: usize) -> Vec<f64> {
+    // counts[i][j] for i values of the first sample and j of the second. The
+    // largest value is either of the first sample, larger than all j, or not.
+    let mut counts = vec![vec![vec![1.0]; n + 1]; m + 1];
+    for i in 1..=m {
+        for j in 1..=n {
+            let mut c = vec![0.0; i * j + 1];
+            for (u, count) in counts[i - 1][j].iter().enumerate() {
+                c[u + j] += count;
+            }
+            for (u, count) in counts[i][j - 1].iter().enumerate() {
+                c[u] += count;
+            }
+            counts[i][j] = c;
+        }
+    }
+    counts.swap_remove(m).swap_remove(n)
+}
+
+/// Wilcoxon signed-rank test of whether the differences of paired samples are
+/// centred on 0. Pairs with no difference are left out. The statistic is the
+/// sum of the ranks of the positive differences `x - y`, and the effect size the
+/// matched-pairs rank-biserial correlation, from -1 when `y` is always larger
+/// to 1 when `x` is.
+pub fn wilcoxon_signed_rank(x: &[f64], y: &[f64]) -> TestResult {
+    assert_eq!(x.len(), y.len(), "samples must be paired");
+    let differences: Vec<f64> = x
+        .iter()
+        .zip(y.iter())
+        .map(|(a, b)| a - b)
+        .filter(|&d| d != 0.0)
+        .collect();
+    let n = differences.len();
+    if n == 0 {
+        return TestResult {
+            statistic: 0.0,
+            p_value: 1.0,
+            effect_size: 0.0,
+        };
+    }
+    let magnitudes: Vec<f64> = differences.iter().map(|d| d.abs()).collect();
+    let ranks = ranks(&magnitudes);
+    let positive: f64 = ranks
+        .iter()
+        .zip(differences.iter())
+        .filter(|(_, &d)| d > 0.0)
+        .fold(0.0, |sum, (r, _)| sum + r);
+    let total = (n * (n + 1)) as f64 / 2.0;
+    let ties = tie_correction(&magnitudes);
+    let p_value = if ties == 0.0 && n <= EXACT_LIMIT {
+        two_sided(&signed_rank_counts(n), positive.round() as usize)
+    } else {
+        let variance = (n * (n + 1) * (2 * n + 1)) as f64 / 24.0 - ties / 48.0;
+        normal_two_sided(positive - total / 2.0, variance)
+    };
+    TestResult {
+        statistic: positive,
+        p_value,
+        effect_size: (2.0 * positive - total) / total,
+    }
+}
+
+/// Number of subsets of the ranks 1 to `n` with each sum.
+fn signed_rank_counts(n: usize) -> Vec<f64> {
+    let mut counts = vec![0.0; n * (n + 1) / 2 + 1];
+    counts[0] = 1.0;
+    for rank in 1..=n {
+        for sum in (rank..counts.len()).rev() {
+            counts[sum] += counts[sum - rank];
+        }
+    }
+    counts
+}
+
+/// Two-sided p-value of an observed statistic from the number of ways each value
+/// of the statistic can happen.
+fn two_sided(counts: &[f64], observed: usize) -> f64 {
+    let total: f64 = counts.iter().sum();
+    let lower: f64 = counts[..=observed].iter().sum();
+    let upper: f64 = counts[observed..].iter().sum();
+    (2.0 * lower.min(upper) / total).min(1.0)
+}
+
+/// Two-sided p-value of a normally distributed statistic `deviation` away from
+/// its mean, with continuity correction.
+fn normal_two_sided(deviation: f64, variance: f64) -> f64 {
+    if variance <= 0.0 {
+        return 1.0;
+    }
+    let z = (deviation.abs() - 0.5).max(0.0) / variance.sqrt();
+    (2.0 * normal_sf(z)).min(1.0)
+}
+
+/// Friedman test of whether `k` treatments differ, from the value of each
+/// treatment on each of the `blocks`, with lower values ranked first. The
+/// effect size is Kendall's W. Also returns the mean rank of each treatment.
+pub fn friedman(blocks: &[Vec<f64>]) -> (TestResult, Vec<f64>) {
+    let b = blocks.len();
+    let k = blocks.first().map_or(0, Vec::len);
+    let mut rank_sums = vec![0.0; k];
+    let mut ties = 0.0;
+    for block in blocks {
+        assert_eq!(
+            block.len(),
+            k,
+            "every block must have a value of every treatment"
+        );
+        for (sum, rank) in rank_sums.iter_mut().zip(ranks(block)) {
+            *sum += rank;
+        }
+        ties += tie_correction(block);
+    }
+    let mean_ranks: Vec<f64> = rank_sums.iter().map(|r| r / b as f64).collect();
+    if b == 0 || k < 2 {
+        let result = TestResult {
+            statistic: 0.0,
+            p_value: 1.0,
+            effect_size: 0.0,
+        };
+        return (result, mean_ranks);
+    }
+    let (bf, kf) = (b as f64, k as f64);
+    let squares: f64 = rank_sums.iter().map(|r| r * r).sum();
+    let uncorrected = 12.0 / (bf * kf * (kf + 1.0)) * squares - 3.0 * bf * (kf + 1.0);
+    let denominator = 1.0 - ties / (bf * kf * (kf * kf - 1.0));
+    let statistic = if denominator > 0.0 {
+        uncorrected / denominator
+    } else {
+        0.0
+    };
+    let result = TestResult {
+        statistic,
+        p_value: chi_square_sf(statistic, kf - 1.0),
+        effect_size: statistic / (bf * (kf - 1.0)),
+    };
+    (result, mean_ranks)
+}
+
+/// Two-sided p-value of the difference between the mean ranks of two of `k`
+/// treatments over `blocks` blocks, for comparisons after a Friedman test.
+pub fn rank_difference_p_value(rank_a: f64, rank_b: f64, k: usize, blocks: usize) -> f64 {
+    let (kf, bf) = (k as f64, blocks as f64);
+    let z = (rank_a - rank_b).abs() / (kf * (kf + 1.0) / (6.0 * bf)).sqrt();
+    (2.0 * normal_sf(z)).min(1.0)
+}
+
+/// Holm's step-down adjustment of p-values for multiple comparisons.
+pub fn holm(p_values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..p_values.len()).collect();
+    order.sort_by(|&a, &b| p_values[a].partial_cmp(&p_values[b]).expect("NaN p-value"));
+    let mut adjusted = vec![0.0; p_values.len()];
+    let mut largest: f64 = 0.0;
+    for (position, &i) in order.iter().enumerate() {
+        let p = (p_values[i] * (p_values.len() - position) as f64).min(1.0);
+        largest = largest.max(p);
+        adjusted[i] = largest;
+    }
+    adjusted
+}
+
+/// Probability that a standard normal variable is larger than `z`.
+pub fn normal_sf(z: f64) -> f64 {
+    0.5 * erfc(z / std::f64::consts::SQRT_2)
+}
+
+/// Complementary error function, with a relative error below 1.2e-7.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let polynomial = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let r = t * polynomial.exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+/// Probability that a chi-square variable with `df` degrees of freedom is
+/// larger than `x`.
+pub fn chi_square_sf(x: f64, df: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    upper_regularized_gamma(df / 2.0, x / 2.0)
+}
+
+/// Q(a, x), from its series for small `x` and its continued fraction otherwise.
+fn upper_regularized_gamma(a: f64, x: f64) -> f64 {
+    let log_prefix = a * x.ln() - x - ln_gamma(a);
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut n = a;
+        for _ in 0..500 {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        1.0 - sum * log_prefix.exp()
+    } else {
+        // modified Lentz's method
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..500 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        log_prefix.exp() * h
+    }
+}
+
+/// Natural logarithm of the gamma function, by Lanczos' approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.120_865_097_386_617_9e-2,
+        -0.539_523_938_495_3e-5,
+    ];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let mut series = 1.000_000_000_190_015;
+    for (i, c) in COEFFICIENTS.iter().enumerate() {
+        series += c / (x + 1.0 + i as f64);
+    }
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn confidence_interval_of_small_sample() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(mean(&values), 5.0);
+        assert_eq!(median(&values), 4.5);
+        assert!((std_dev(&values) - 2.138).abs() < 1e-3);
+        let (low, high) = mean_confidence_interval(&values);
+        // 2.365 * 2.138 / sqrt(8)
+        assert!((high - 5.0 - 1.788).abs() < 1e-3);
+        assert!((5.0 - low - 1.788).abs() < 1e-3);
+        assert_eq!(mean_confidence_interval(&[3.0]), (3.0, 3.0));
+    }
+
+    #[test]
+    fn nonparametric_tests_match_known_values() {
+        assert_eq!(ranks(&[3.0, 1.0, 3.0, 2.0]), vec![3.5, 1.0, 3.5, 2.0]);
+
+        let separated = mann_whitney_u(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]);
+        assert_eq!(separated.statistic, 0.0);
+        assert!((separated.p_value - 0.1).abs() < 1e-12);
+        assert_eq!(separated.effect_size, 0.0);
+
+        let larger = wilcoxon_signed_rank(&[2.0, 4.0, 6.0, 8.0, 10.0], &[1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(larger.statistic, 15.0);
+        assert!((larger.p_value - 0.0625).abs() < 1e-12);
+        assert_eq!(larger.effect_size, 1.0);
+
+        // same ordering of 3 treatments on 4 blocks
+        let blocks = vec![vec![1.0, 2.0, 3.0]; 4];
+        let (result, mean_ranks) = friedman(&blocks);
+        assert!((result.statistic - 8.0).abs() < 1e-12);
+        assert!((result.p_value - (-4.0f64).exp()).abs() < 1e-9);
+        assert!((result.effect_size - 1.0).abs() < 1e-12);
+        assert_eq!(mean_ranks, vec![1.0, 2.0, 3.0]);
+
+        assert!((normal_sf(1.96) - 0.025).abs() < 1e-4);
+        assert!((chi_square_sf(3.84, 1.0) - 0.05).abs() < 1e-3);
+        assert!((chi_square_sf(0.5, 2.0) - (-0.25f64).exp()).abs() < 1e-9);
+        assert_eq!(holm(&[0.01, 0.04, 0.03]), vec![0.03, 0.06, 0.06]);
+    }
+}
@@ -0,0 +1,424 @@
+//! Drawing of instances, tours and pheromone trails, and of charts of results,
+//! as SVG images.
+
+use crate::util::EdgeValues;
+use std::fmt::Write;
+
+/// How an instance is drawn by `plot_svg`.
+#[derive(Debug, Clone)]
+pub struct PlotOptions {
+    /// Width of the image in pixels. The height follows from the shape of the
+    /// instance.
+    pub width: f64,
+    /// Number of edges with the most pheromone drawn for each city
+    pub pheromone_edges: usize,
+    /// Text written at the top of the image
+    pub title: Option<String>,
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        PlotOptions {
+            width: 800.0,
+            pheromone_edges: 5,
+            title: None,
+        }
+    }
+}
+
+const MARGIN: f64 = 20.0;
+const TITLE_HEIGHT: f64 = 20.0;
+
+/// Draws the cities at the given coordinates, and optionally a tour and the
+/// edges with the most pheromone. The more pheromone an edge has, the wider and
+/// more opaque it is drawn.
+pub fn plot_svg(
+    coordinates: &[(f64, f64)],
+    tour: Option<&[u32]>,
+    pheromones: Option<&EdgeValues>,
+    options: &PlotOptions,
+) -> String {
+    let (min_x, max_x, min_y, max_y) = coordinates.iter().fold(
+        (
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |(min_x, max_x, min_y, max_y), &(x, y)| {
+            (min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y))
+        },
+    );
+    let scale = (options.width - 2.0 * MARGIN) / (max_x - min_x).max(max_y - min_y).max(1e-9);
+    let top = if options.title.is_some() {
+        MARGIN + TITLE_HEIGHT
+    } else {
+        MARGIN
+    };
+    let height = top + MARGIN + (max_y - min_y).max(0.0) * scale;
+    // y grows downwards in SVG, so it is flipped
+    let point = |city: usize| {
+        let (x, y) = coordinates[city];
+        (MARGIN + (x - min_x) * scale, top + (max_y - y) * scale)
+    };
+
+    let mut svg = String::new();
+    // writing to a String never fails
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.0} {:.0}">"#,
+        options.width, height, options.width, height
+    );
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+    if let Some(title) = &options.title {
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" font-family="sans-serif" font-size="14">{}</text>"#,
+            MARGIN,
+            MARGIN + 10.0,
+            escape(title)
+        );
+    }
+    if let Some(pheromones) = pheromones {
+        let edges = strongest_edges(pheromones, coordinates.len(), options.pheromone_edges);
+        let max = edges.iter().map(|&(_, _, v)| v).fold(0.0, f64::max);
+        let _ = writeln!(svg, r##"<g stroke="#d62728" stroke-linecap="round">"##);
+        for (i, j, value) in edges {
+            let strength = if max > 0.0 { value / max } else { 0.0 };
+            let ((x1, y1), (x2, y2)) = (point(i), point(j));
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke-width="{:.2}" stroke-opacity="{:.3}"/>"#,
+                x1,
+                y1,
+                x2,
+                y2,
+                0.5 + 2.5 * strength,
+                strength
+            );
+        }
+        let _ = writeln!(svg, "</g>");
+    }
+    if let Some(tour) = tour {
+        let points: Vec<String> = tour
+            .iter()
+            .map(|&city| {
+                let (x, y) = point(city as usize);
+                format!("{:.1},{:.1}", x, y)
+            })
+            .collect();
+        let _ = writeln!(
+            svg,
+            r##"<polygon points="{}" fill="none" stroke="#1f77b4" stroke-width="1.5"/>"##,
+            points.join(" ")
+        );
+    }
+    // smaller cities for larger instances, so they don't cover the tour
+    let radius = (30.0 / (coordinates.len() as f64).sqrt()).clamp(0.5, 3.0);
+    let _ = writeln!(svg, r#"<g fill="black">"#);
+    for city in 0..coordinates.len() {
+        let (x, y) = point(city);
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}"/>"#,
+            x, y, radius
+        );
+    }
+    let _ = writeln!(svg, "</g>");
+    let _ = writeln!(svg, "</svg>");
+    svg
+}
+
+/// The `k` edges of each city with the most pheromone, each edge only once,
+/// as (i, j, pheromone) with i < j. Edges of asymmetric instances count the
+/// larger pheromone of their two directions. Only candidate edges are
+/// considered when pheromones are only stored for them.
+fn strongest_edges(pheromones: &EdgeValues, size: usize, k: usize) -> Vec<(usize, usize, f64)> {
+    let mut edges = Vec::with_capacity(size * k);
+    if k == 0 {
+        return edges;
+    }
+    for i in 0..size {
+        let mut row: Vec<(usize, f64)> = match pheromones.candidates() {
+            Some(candidates) => candidates
+                .of(i)
+                .iter()
+                .map(|&j| (j as usize, pheromones.get(i, j as usize)))
+                .collect(),
+            None => (0..size)
+                .filter(|&j| j != i)
+                .map(|j| (j, pheromones.get(i, j).max(pheromones.get(j, i))))
+                .collect(),
+        };
+        let by_pheromone =
+            |a: &(usize, f64), b: &(usize, f64)| b.1.partial_cmp(&a.1).expect("failed comparison");
+        if row.len() > k {
+            row.select_nth_unstable_by(k - 1, by_pheromone);
+            row.truncate(k);
+        }
+        edges.extend(row.into_iter().map(|(j, v)| (i.min(j), i.max(j), v)));
+    }
+    edges.sort_by_key(|&(i, j, _)| (i, j));
+    edges.dedup_by_key(|&mut (i, j, _)| (i, j));
+    edges
+}
+
+/// A line of a chart, with an optional band around it such as a confidence
+/// interval.
+#[derive(Debug, Clone, Default)]
+pub struct Series {
+    pub label: String,
+    /// (x, y) points, in order of x
+    pub points: Vec<(f64, f64)>,
+    /// (x, low, high) points of the band, in order of x. Empty for no band.
+    pub band: Vec<(f64, f64, f64)>,
+}
+
+/// Text and size of a chart drawn by `line_chart`.
+#[derive(Debug, Clone)]
+pub struct ChartOptions {
+    pub title: String,
+    pub x_label: String,
+    pub y_label: String,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Default for ChartOptions {
+    fn default() -> Self {
+        ChartOptions {
+            title: String::new(),
+            x_label: String::new(),
+            y_label: String::new(),
+            width: 640.0,
+            height: 360.0,
+        }
+    }
+}
+
+/// Colours of the series of a chart, repeated if there are more series.
+const PALETTE: [&str; 10] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+    "#bcbd22", "#17becf",
+];
+
+/// Draws a line chart with axes, a legend and the band of each series as a
+/// lighter area behind its line.
+pub fn line_chart(series: &[Series], options: &ChartOptions) -> String {
+    let (left, right, top, bottom) = (70.0, 160.0, 35.0, 45.0);
+    let plot_width = options.width - left - right;
+    let plot_height = options.height - top - bottom;
+    let xs = series.iter().flat_map(|s| {
+        let band = s.band.iter().map(|&(x, _, _)| x);
+        s.points.iter().map(|&(x, _)| x).chain(band)
+    });
+    let ys = series.iter().flat_map(|s| {
+        let band = s.band.iter().flat_map(|&(_, low, high)| vec![low, high]);
+        s.points.iter().map(|&(_, y)| y).chain(band)
+    });
+    let (min_x, max_x) = padded_range(xs, 0.0);
+    let (min_y, max_y) = padded_range(ys, 0.05);
+    let to_x = |x: f64| left + (x - min_x) / (max_x - min_x) * plot_width;
+    let to_y = |y: f64| top + (max_y - y) / (max_y - min_y) * plot_height;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.0} {:.0}" font-family="sans-serif" font-size="11">"#,
+        options.width, options.height, options.width, options.height
+    );
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+    let _ = writeln!(
+        svg,
+        r#"<text x="{:.1}" y="20" font-size="14">{}</text>"#,
+        left,
+        escape(&options.title)
+    );
+    // grid and ticks
+    for x in nice_ticks(min_x, max_x) {
+        let px = to_x(x);
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#ddd"/><text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"##,
+            px,
+            top,
+            px,
+            top + plot_height,
+            px,
+            top + plot_height + 15.0,
+            format_tick(x, tick_step(min_x, max_x))
+        );
+    }
+    for y in nice_ticks(min_y, max_y) {
+        let py = to_y(y);
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#ddd"/><text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"##,
+            left,
+            py,
+            left + plot_width,
+            py,
+            left - 5.0,
+            py + 4.0,
+            format_tick(y, tick_step(min_y, max_y))
+        );
+    }
+    let _ = writeln!(
+        svg,
+        r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="none" stroke="black"/>"#,
+        left, top, plot_width, plot_height
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+        left + plot_width / 2.0,
+        options.height - 8.0,
+        escape(&options.x_label)
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text transform="translate(15 {:.1}) rotate(-90)" text-anchor="middle">{}</text>"#,
+        top + plot_height / 2.0,
+        escape(&options.y_label)
+    );
+    for (i, s) in series.iter().enumerate() {
+        let colour = PALETTE[i % PALETTE.len()];
+        if !s.band.is_empty() {
+            let upper = s.band.iter().map(|&(x, _, high)| (x, high));
+            let lower = s.band.iter().rev().map(|&(x, low, _)| (x, low));
+            let points: Vec<String> = upper
+                .chain(lower)
+                .map(|(x, y)| format!("{:.1},{:.1}", to_x(x), to_y(y)))
+                .collect();
+            let _ = writeln!(
+                svg,
+                r#"<polygon points="{}" fill="{}" fill-opacity="0.2" stroke="none"/>"#,
+                points.join(" "),
+                colour
+            );
+        }
+        let points: Vec<String> = s
+            .points
+            .iter()
+            .map(|&(x, y)| format!("{:.1},{:.1}", to_x(x), to_y(y)))
+            .collect();
+        let _ = writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="1.5"/>"#,
+            points.join(" "),
+            colour
+        );
+        let legend_y = top + 10.0 + i as f64 * 16.0;
+        let legend_x = left + plot_width + 10.0;
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{:.1}" y="{:.1}" width="12" height="3" fill="{}"/><text x="{:.1}" y="{:.1}">{}</text>"#,
+            legend_x,
+            legend_y - 4.0,
+            colour,
+            legend_x + 16.0,
+            legend_y,
+            escape(&s.label)
+        );
+    }
+    let _ = writeln!(svg, "</svg>");
+    svg
+}
+
+/// Smallest and largest values, with `padding` as a fraction of the range added
+/// on each side. Never empty, so that values can always be scaled to it.
+fn padded_range(values: impl Iterator<Item = f64>, padding: f64) -> (f64, f64) {
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+        (min.min(v), max.max(v))
+    });
+    if !min.is_finite() || !max.is_finite() {
+        return (0.0, 1.0);
+    }
+    if max - min <= f64::EPSILON * max.abs().max(1.0) {
+        return (min - 1.0, max + 1.0);
+    }
+    let pad = (max - min) * padding;
+    (min - pad, max + pad)
+}
+
+/// Distance between ticks on an axis from `min` to `max`, a round number that
+/// gives around 6 ticks.
+fn tick_step(min: f64, max: f64) -> f64 {
+    let rough = (max - min) / 6.0;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|&step| step >= rough)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// Round values between `min` and `max` to put ticks at.
+fn nice_ticks(min: f64, max: f64) -> Vec<f64> {
+    let step = tick_step(min, max);
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
+
+/// Writes a tick with as many decimals as are needed to tell apart ticks
+/// `step` apart.
+fn format_tick(value: f64, step: f64) -> String {
+    let decimals = (-step.log10().floor()).clamp(0.0, 6.0) as usize;
+    format!("{:.*}", decimals, value)
+}
+
+/// Escapes text to be written inside SVG or HTML elements.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::generate_pheromone_matrix;
+
+    #[test]
+    fn chart_ticks_are_round_numbers() {
+        assert_eq!(nice_ticks(0.0, 10.0), vec![0.0, 2.0, 4.0, 6.0, 8.0, 10.0]);
+        assert_eq!(nice_ticks(6520.0, 7290.0).first(), Some(&6600.0));
+        assert_eq!(format_tick(6600.0, 200.0), "6600");
+        assert_eq!(format_tick(0.015, 0.005), "0.015");
+        let series = Series {
+            label: "Mmas".to_owned(),
+            points: vec![(1.0, 10.0), (2.0, 8.0)],
+            band: vec![(1.0, 9.0, 11.0), (2.0, 7.5, 8.5)],
+        };
+        let svg = line_chart(&[series], &ChartOptions::default());
+        assert_eq!(svg.matches("<polyline").count(), 1);
+        assert_eq!(svg.matches("<polygon").count(), 1);
+    }
+
+    #[test]
+    fn plot_draws_cities_tour_and_strongest_edges() {
+        let coordinates = [(0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0)];
+        let mut pheromones = EdgeValues::Full(generate_pheromone_matrix(4, 1.0));
+        // the square's sides have more pheromone than its diagonals
+        for i in 0..4 {
+            pheromones.set(i, (i + 1) % 4, 2.0);
+        }
+        let options = PlotOptions {
+            pheromone_edges: 2,
+            title: Some("a < b".to_owned()),
+            ..PlotOptions::default()
+        };
+        let svg = plot_svg(
+            &coordinates,
+            Some(&[0, 1, 2, 3]),
+            Some(&pheromones),
+            &options,
+        );
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert_eq!(svg.matches("<line").count(), 4);
+        assert_eq!(svg.matches(r#"stroke-opacity="1.000""#).count(), 4);
+        assert!(svg.contains("<polygon"));
+        assert!(svg.contains("a &lt; b"));
+    }
+}
@@ -0,0 +1,18 @@
+//! Helpers shared by the tests of the library and of the command-line program,
+//! which is why this file is a module of both crates.
+
+use std::path::PathBuf;
+
+/// Empty directory for the files of a test, unique to the test and the process
+/// running it, so tests run in parallel or by concurrent jobs don't overwrite
+/// each other's files.
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "parallelized_acotsp_{}_{}",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("failed creating test directory");
+    dir
+}
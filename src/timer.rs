@@ -1,28 +1,34 @@
-use lazy_static::lazy_static;
-use parking_lot::Mutex;
 use std::time::{Duration, Instant};
 
-// is this even the best way to do this??
-lazy_static! {
-    static ref TIMER: Mutex<Stopwatch> = Mutex::new(Stopwatch::new());
-}
-
-struct Stopwatch {
+/// Measures the time elapsed since a run started. Each run owns its own
+/// stopwatch, so several runs can execute at the same time.
+#[derive(Clone, Copy)]
+pub struct Stopwatch {
     start: Instant,
 }
 
 impl Stopwatch {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             start: Instant::now(),
         }
     }
-}
 
-pub fn restart_timer() {
-    TIMER.lock().start = Instant::now();
+    /// Creates a stopwatch as if it had already been running for `elapsed`.
+    /// Used when resuming a run from a checkpoint.
+    pub fn starting_from(elapsed: Duration) -> Self {
+        Self {
+            start: Instant::now() - elapsed,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
 }
 
-pub fn elapsed() -> Duration {
-    TIMER.lock().start.elapsed()
+impl Default for Stopwatch {
+    fn default() -> Self {
+        Self::new()
+    }
 }
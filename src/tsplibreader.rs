@@ -1,84 +1,319 @@
-use crate::instance_data::{FileData, InstanceData, Metadata};
+use crate::aco::Ant;
+use crate::instance_data::EdgeWeightType::*;
+use crate::instance_data::{Coordinates, FileData, InstanceData, Metadata};
+use crate::util::{check_tour, value_of_tour, TourError};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::iter::Peekable;
 
 pub fn read_instance_file(f_name: &str) -> FileData {
-    use crate::instance_data::DataDescriptionType::*;
-
     let f = File::open(f_name).expect("Failed opening file");
-    let mut lines = BufReader::new(&f).lines();
+    read_instance(BufReader::new(f))
+}
+
+/// Reads an instance in the TSPLIB format. Distances can be calculated from a
+/// NODE_COORD_SECTION (EUC_2D or EUC_3D) or given as a FULL_MATRIX in an
+/// EDGE_WEIGHT_SECTION (EXPLICIT).
+pub fn read_instance(input: impl BufRead) -> FileData {
+    let mut lines = input
+        .lines()
+        .map(|line| line.expect("failed reading instance"))
+        .peekable();
     let mut metadata = Metadata::default();
-    let mut size: usize = 0;
-    // this enum only has a single variant, so it's basically a unit
-    // struct.  Since no other variants exist we could remove the part
-    // of the code that checks for it, but I'm leaving it here so that
-    // it's easier to change it if/when more variants are added. Just have to
-    // change this to `let mut data_layout = <initial value (might be optional)>`
-    // and add the other variants to the match statement
-    let data_layout;
-    loop {
-        let line = lines.next().unwrap().unwrap();
-        let split: Vec<_> = line.split(": ").collect();
-        match split[0].trim() {
-            "NAME" => metadata.name = split[1].to_owned(),
-            "DIMENSION" => size = split[1].parse().unwrap(),
-            "EDGE_WEIGHT_TYPE" => metadata.edge_weight_type = Some(split[1].parse().unwrap()),
+    let mut coordinates = None;
+    let mut display_data = None;
+    let mut edge_weights = None;
+    while let Some(line) = lines.next() {
+        let mut split = line.splitn(2, ':');
+        let key = split.next().unwrap_or("").trim();
+        let value = split.next().unwrap_or("").trim();
+        match key {
+            "NAME" => metadata.name = value.to_owned(),
+            "TYPE" => metadata.problem_type = Some(value.to_owned()),
+            "COMMENT" => {
+                metadata.comment = Some(match metadata.comment.take() {
+                    Some(comment) => format!("{}\n{}", comment, value),
+                    None => value.to_owned(),
+                })
+            }
+            "DIMENSION" => metadata.dimension = Some(value.parse().expect("invalid DIMENSION")),
+            "EDGE_WEIGHT_TYPE" => {
+                metadata.edge_weight_type = Some(value.parse().unwrap_or_else(|e| panic!("{}", e)))
+            }
+            "EDGE_WEIGHT_FORMAT" => metadata.edge_weight_format = Some(value.to_owned()),
+            "DISPLAY_DATA_TYPE" => metadata.display_data_type = Some(value.to_owned()),
             "NODE_COORD_SECTION" => {
-                data_layout = NODE_COORD_SECTION;
-                break;
+                let nodes = read_nodes(&mut lines, metadata.dimension.unwrap_or(0), key);
+                coordinates = Some(if nodes.first().is_some_and(|node| node.len() == 3) {
+                    Coordinates::ThreeD(nodes.iter().map(|n| (n[0], n[1], n[2])).collect())
+                } else {
+                    Coordinates::TwoD(nodes.iter().map(|n| (n[0], n[1])).collect())
+                });
+            }
+            "EDGE_WEIGHT_SECTION" => {
+                let format = metadata.edge_weight_format.as_deref();
+                if format != Some("FULL_MATRIX") {
+                    panic!(
+                        "unsupported EDGE_WEIGHT_FORMAT {}",
+                        format.unwrap_or("(none)")
+                    );
+                }
+                let size = metadata
+                    .dimension
+                    .expect("EDGE_WEIGHT_SECTION needs a DIMENSION");
+                edge_weights = Some(read_full_matrix(&mut lines, size));
             }
+            "DISPLAY_DATA_SECTION" => {
+                let nodes = read_nodes(&mut lines, metadata.dimension.unwrap_or(0), key);
+                assert!(
+                    nodes.first().is_none_or(|node| node.len() == 2),
+                    "DISPLAY_DATA_SECTION must have 2 coordinates per node"
+                );
+                display_data = Some(nodes.iter().map(|n| (n[0], n[1])).collect());
+            }
+            "EOF" => break,
             _ => (),
         }
     }
 
-    let instance_data = match data_layout {
-        NODE_COORD_SECTION => read_node_coord_section(lines, &metadata, size),
-        // all instances used so far have been NODE_COORD_SECTION, so no
-        // other modes have been implemented yet
-        // _ => unimplemented!(),
+    let data = match (metadata.edge_weight_type, &coordinates) {
+        (Some(EXPLICIT), _) => {
+            let distances = edge_weights.as_ref().expect("no EDGE_WEIGHT_SECTION");
+            InstanceData::from_distance_matrix(distances)
+        }
+        (Some(EUC_2D), Some(Coordinates::TwoD(nodes))) => InstanceData::from_coordinates(nodes),
+        (Some(EUC_3D), Some(Coordinates::ThreeD(nodes))) => {
+            InstanceData::from_3d_coordinates(nodes)
+        }
+        (Some(edge_weight_type), Some(_)) => panic!(
+            "NODE_COORD_SECTION does not match EDGE_WEIGHT_TYPE {:?}",
+            edge_weight_type
+        ),
+        (None, _) => panic!("No defined edge_weight_type"),
+        (Some(edge_weight_type), None) => panic!(
+            "EDGE_WEIGHT_TYPE {:?} needs a NODE_COORD_SECTION",
+            edge_weight_type
+        ),
     };
-
+    if let Some(dimension) = metadata.dimension {
+        assert_eq!(
+            dimension, data.size,
+            "DIMENSION does not match the number of cities"
+        );
+    }
     FileData {
         metadata,
-        data: instance_data,
+        data,
+        coordinates,
+        display_data,
     }
 }
 
-fn read_node_coord_section(
-    lines: std::io::Lines<BufReader<&File>>,
-    metadata: &Metadata,
+/// Reads the lines of a section with the number of a node followed by its
+/// coordinates, until a line that does not start with a number. Every node must
+/// have the same number of coordinates, 2 or 3.
+fn read_nodes(
+    lines: &mut Peekable<impl Iterator<Item = String>>,
     size: usize,
-) -> InstanceData {
-    use crate::instance_data::EdgeWeightType::*;
-    use crate::util::distance_funcs;
+    section: &str,
+) -> Vec<Vec<f64>> {
+    let mut nodes: Vec<Vec<f64>> = Vec::with_capacity(size);
+    while let Some(line) = lines.peek() {
+        let mut split = line.split_whitespace();
+        let id = match split.next() {
+            Some(id) if id.parse::<f64>().is_ok() => id.to_owned(),
+            _ => break,
+        };
+        let node: Vec<f64> = split
+            .map(|value| value.parse().expect("invalid coordinate"))
+            .collect();
+        let expected = nodes.first().map_or(node.len(), Vec::len);
+        assert!(
+            (node.len() == 2 || node.len() == 3) && node.len() == expected,
+            "node {} of {} has {} coordinates, expected {}",
+            id,
+            section,
+            node.len(),
+            if nodes.is_empty() {
+                "2 or 3".to_owned()
+            } else {
+                expected.to_string()
+            }
+        );
+        nodes.push(node);
+        lines.next();
+    }
+    nodes
+}
 
-    let mut nodes: Vec<(i32, i32)> = Vec::with_capacity(size);
+/// Reads the `size` x `size` distances of a FULL_MATRIX, which may be split over
+/// lines in any way.
+fn read_full_matrix(lines: &mut impl Iterator<Item = String>, size: usize) -> Vec<Vec<u32>> {
+    let mut values = Vec::with_capacity(size * size);
     for line in lines {
-        let line = line.unwrap();
-        if line.trim() == "EOF" {
+        for token in line.split_whitespace() {
+            values.push(token.parse().expect("invalid edge weight"));
+        }
+        if values.len() >= size * size {
             break;
         }
-        let split: Vec<_> = line.split_whitespace().collect();
-        nodes.push((split[1].parse().unwrap(), split[2].parse().unwrap()));
     }
-    let mut distances = crate::util::IntegerMatrix::with_capacity(size);
-    for (i, &node_i) in nodes.iter().enumerate() {
-        for (j, &node_j) in nodes.iter().enumerate() {
-            if i == j {
-                distances.push(std::u32::MAX);
-                continue;
-            }
-            match metadata
-                .edge_weight_type
-                .as_ref()
-                .expect("No defined edge_weight_type")
-            {
-                EUC_2D => distances.push(distance_funcs::euc_2d(node_i, node_j)),
-                // all instances used so far have used EUC_2D, so no
-                // other modes have been implemented yet
-                // _ => unimplemented!(),
+    assert_eq!(
+        values.len(),
+        size * size,
+        "EDGE_WEIGHT_SECTION does not have DIMENSION x DIMENSION weights"
+    );
+    values.chunks(size).map(|row| row.to_vec()).collect()
+}
+
+/// Tour read from a TSPLIB `TOUR` file, with cities numbered from 0.
+#[derive(Debug, Clone)]
+pub struct TourFile {
+    pub name: String,
+    /// Number of cities given in the header, if any
+    pub dimension: Option<usize>,
+    pub tour: Vec<usize>,
+}
+
+impl TourFile {
+    /// Checks that the tour visits every city of the instance exactly once.
+    pub fn check(&self, data: &InstanceData) -> Result<(), TourError> {
+        match self.dimension {
+            Some(dimension) if dimension != data.size => Err(TourError::WrongLength {
+                expected: data.size,
+                found: dimension,
+            }),
+            _ => check_tour(&self.tour, data.size),
+        }
+    }
+
+    /// Length of the tour on the instance, if it is a tour of the instance.
+    pub fn length(&self, data: &InstanceData) -> Result<u32, TourError> {
+        self.check(data)?;
+        Ok(value_of_tour(data, &self.tour))
+    }
+
+    /// An ant that followed the tour, if it is a tour of the instance.
+    pub fn to_ant(&self, data: &InstanceData) -> Result<Ant, TourError> {
+        self.check(data)?;
+        Ok(Ant::from_tour(data, &self.tour))
+    }
+}
+
+pub fn read_tour_file(f_name: &str) -> TourFile {
+    let f = File::open(f_name).expect("Failed opening tour file");
+    read_tour(BufReader::new(f))
+}
+
+/// Reads a tour in the TSPLIB `TOUR` format. The cities of the tour section may
+/// be split over any number of lines, and end at -1 or at the end of the file.
+pub fn read_tour(input: impl BufRead) -> TourFile {
+    let mut lines = input.lines().map(|line| line.expect("failed reading tour"));
+    let mut tour_file = TourFile {
+        name: String::new(),
+        dimension: None,
+        tour: Vec::new(),
+    };
+    for line in &mut lines {
+        let mut split = line.splitn(2, ':');
+        let key = split.next().unwrap_or("").trim();
+        let value = split.next().unwrap_or("").trim();
+        match key {
+            "NAME" => tour_file.name = value.to_owned(),
+            "DIMENSION" => tour_file.dimension = Some(value.parse().expect("invalid DIMENSION")),
+            "TYPE" if value != "TOUR" => panic!("file is of TYPE {}, not TOUR", value),
+            "TOUR_SECTION" => break,
+            _ => (),
+        }
+    }
+    'section: for line in lines {
+        for token in line.split_whitespace() {
+            if token == "-1" || token == "EOF" {
+                break 'section;
             }
+            let city: usize = token.parse().expect("invalid city in TOUR_SECTION");
+            assert!(city > 0, "cities in TOUR_SECTION are numbered from 1");
+            tour_file.tour.push(city - 1);
         }
     }
-    InstanceData { size, distances }
+    tour_file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn instance_keeps_coordinates_and_metadata() {
+        let text = "NAME: tri\nTYPE: TSP\nCOMMENT: first\nCOMMENT : second\nDIMENSION: 3\n\
+                    EDGE_WEIGHT_TYPE : EUC_2D\nDISPLAY_DATA_TYPE: TWOD_DISPLAY\n\
+                    NODE_COORD_SECTION\n1 0 0\n2 0 3\n3 4 3\n\
+                    DISPLAY_DATA_SECTION\n1 1.5 0\n2 0 1\n3 1 1\nEOF\n";
+        let instance = read_instance(text.as_bytes());
+        let metadata = &instance.metadata;
+        assert_eq!(metadata.name, "tri");
+        assert_eq!(metadata.problem_type.as_deref(), Some("TSP"));
+        assert_eq!(metadata.comment.as_deref(), Some("first\nsecond"));
+        assert_eq!(metadata.dimension, Some(3));
+        assert_eq!(metadata.display_data_type.as_deref(), Some("TWOD_DISPLAY"));
+        assert_eq!(
+            instance.coordinates,
+            Some(Coordinates::TwoD(vec![(0.0, 0.0), (0.0, 3.0), (4.0, 3.0)]))
+        );
+        assert_eq!(
+            instance.display_data,
+            Some(vec![(1.5, 0.0), (0.0, 1.0), (1.0, 1.0)])
+        );
+        assert_eq!(instance.data.distance(0, 2), 5);
+
+        let text = "NAME: line\nDIMENSION: 2\nEDGE_WEIGHT_TYPE: EUC_3D\n\
+                    NODE_COORD_SECTION\n1 0 0 0\n2 2 3 6\nEOF\n";
+        let instance = read_instance(text.as_bytes());
+        assert_eq!(instance.coordinates.map(|c| c.len()), Some(2));
+        assert_eq!(instance.data.distance(0, 1), 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "EDGE_WEIGHT_TYPE EUC_2D needs a NODE_COORD_SECTION")]
+    fn instance_without_coordinates_is_rejected() {
+        read_instance("NAME: empty\nDIMENSION: 3\nEDGE_WEIGHT_TYPE: EUC_2D\nEOF\n".as_bytes());
+    }
+
+    #[test]
+    #[should_panic(expected = "node 2 of NODE_COORD_SECTION has 1 coordinates, expected 2")]
+    fn node_without_every_coordinate_is_rejected() {
+        let text = "NAME: short\nEDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION\n1 0 0\n2 5\nEOF\n";
+        read_instance(text.as_bytes());
+    }
+
+    #[test]
+    #[should_panic(expected = "node 1 of DISPLAY_DATA_SECTION has 4 coordinates, expected 2 or 3")]
+    fn node_with_too_many_coordinates_is_rejected() {
+        let text = "NAME: long\nEDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION\n1 0 0\n2 0 1\n\
+                    DISPLAY_DATA_SECTION\n1 0 0 0 0\nEOF\n";
+        read_instance(text.as_bytes());
+    }
+
+    #[test]
+    fn tour_file_is_checked_against_instance() {
+        let data =
+            InstanceData::from_coordinates(&[(0.0, 0.0), (0.0, 3.0), (4.0, 3.0), (4.0, 0.0)]);
+        let text = "NAME : square.opt.tour\nTYPE : TOUR\nDIMENSION : 4\nTOUR_SECTION\n1 2\n3\n4\n-1\nEOF\n";
+        let tour_file = read_tour(text.as_bytes());
+        assert_eq!(tour_file.name, "square.opt.tour");
+        assert_eq!(tour_file.tour, vec![0, 1, 2, 3]);
+        assert_eq!(tour_file.length(&data), Ok(14));
+        assert_eq!(tour_file.to_ant(&data).unwrap().length, 14);
+
+        let repeated = read_tour("TOUR_SECTION\n1 2 2 4\n-1\n".as_bytes());
+        assert_eq!(repeated.length(&data), Err(TourError::RepeatedCity(1)));
+        let larger = read_tour("DIMENSION: 5\nTOUR_SECTION\n1 2 3 4 5\n".as_bytes());
+        assert_eq!(
+            larger.length(&data),
+            Err(TourError::WrongLength {
+                expected: 4,
+                found: 5
+            })
+        );
+    }
 }
@@ -0,0 +1,115 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Writes a tour in the TSPLIB `TOUR` format, in which cities are numbered
+/// from 1.
+pub fn write_tour(out: &mut impl Write, name: &str, comment: &str, tour: &[u32]) -> io::Result<()> {
+    writeln!(out, "NAME: {}", name)?;
+    writeln!(out, "COMMENT: {}", comment)?;
+    writeln!(out, "TYPE: TOUR")?;
+    writeln!(out, "DIMENSION: {}", tour.len())?;
+    writeln!(out, "TOUR_SECTION")?;
+    for city in tour {
+        writeln!(out, "{}", city + 1)?;
+    }
+    writeln!(out, "-1")?;
+    writeln!(out, "EOF")
+}
+
+/// Writes a tour file with `write_tour`, named after the file. The tour is
+/// written to a temporary file that then replaces `path`, so the file never
+/// holds a partially written tour.
+pub fn write_tour_file(path: &Path, comment: &str, tour: &[u32]) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let partial = path.with_extension("tour.partial");
+    {
+        let mut out = BufWriter::new(File::create(&partial)?);
+        write_tour(&mut out, &name, comment, tour)?;
+        out.flush()?;
+    }
+    fs::rename(&partial, path)
+}
+
+/// Writes an instance whose distances are the rounded euclidean distances
+/// between the coordinates of its cities (EUC_2D).
+pub fn write_coordinates_instance(
+    out: &mut impl Write,
+    name: &str,
+    comment: &str,
+    coordinates: &[(f64, f64)],
+) -> io::Result<()> {
+    writeln!(out, "NAME: {}", name)?;
+    writeln!(out, "COMMENT: {}", comment)?;
+    writeln!(out, "TYPE: TSP")?;
+    writeln!(out, "DIMENSION: {}", coordinates.len())?;
+    writeln!(out, "EDGE_WEIGHT_TYPE: EUC_2D")?;
+    writeln!(out, "NODE_COORD_SECTION")?;
+    for (i, (x, y)) in coordinates.iter().enumerate() {
+        writeln!(out, "{} {} {}", i + 1, x, y)?;
+    }
+    writeln!(out, "EOF")
+}
+
+/// Writes an instance given by the distance from every city to every other one,
+/// as a FULL_MATRIX. Instances whose matrix is not symmetric are of TYPE ATSP.
+pub fn write_matrix_instance(
+    out: &mut impl Write,
+    name: &str,
+    comment: &str,
+    distances: &[Vec<u32>],
+) -> io::Result<()> {
+    let size = distances.len();
+    let symmetric = (0..size).all(|i| (0..i).all(|j| distances[i][j] == distances[j][i]));
+    writeln!(out, "NAME: {}", name)?;
+    writeln!(out, "COMMENT: {}", comment)?;
+    writeln!(out, "TYPE: {}", if symmetric { "TSP" } else { "ATSP" })?;
+    writeln!(out, "DIMENSION: {}", size)?;
+    writeln!(out, "EDGE_WEIGHT_TYPE: EXPLICIT")?;
+    writeln!(out, "EDGE_WEIGHT_FORMAT: FULL_MATRIX")?;
+    writeln!(out, "EDGE_WEIGHT_SECTION")?;
+    for row in distances {
+        let row: Vec<String> = row.iter().map(u32::to_string).collect();
+        writeln!(out, "{}", row.join(" "))?;
+    }
+    writeln!(out, "EOF")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tsplibreader::read_instance;
+
+    #[test]
+    fn tour_is_written_in_tsplib_format() {
+        let mut out = Vec::new();
+        write_tour(&mut out, "square.tour", "Length 40", &[0, 2, 1]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "NAME: square.tour\nCOMMENT: Length 40\nTYPE: TOUR\nDIMENSION: 3\n\
+             TOUR_SECTION\n1\n3\n2\n-1\nEOF\n"
+        );
+    }
+
+    #[test]
+    fn written_instances_are_read_back() {
+        let mut out = Vec::new();
+        let coordinates = [(0.0, 0.0), (0.0, 3.0), (4.5, 3.0)];
+        write_coordinates_instance(&mut out, "tri", "test", &coordinates).unwrap();
+        let instance = read_instance(out.as_slice());
+        assert_eq!(instance.metadata.name, "tri");
+        assert_eq!(instance.data.distance(1, 2), 5);
+
+        let mut out = Vec::new();
+        let distances = [vec![0, 1, 2], vec![3, 0, 4], vec![5, 6, 0]];
+        write_matrix_instance(&mut out, "asym", "test", &distances).unwrap();
+        let instance = read_instance(out.as_slice());
+        assert_eq!(instance.metadata.problem_type.as_deref(), Some("ATSP"));
+        assert!(!instance.data.is_symmetric());
+        assert_eq!(instance.data.distance(1, 0), 3);
+        assert_eq!(instance.data.distance(2, 1), 6);
+    }
+}
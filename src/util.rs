@@ -1,5 +1,9 @@
-use indexmap::IndexSet;
+use crate::aco::kernel;
+use crate::instance_data::InstanceData;
 use parking_lot::RwLock;
+use rayon::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
 use std::ops::{Index, IndexMut};
 use std::sync::Arc;
 
@@ -9,7 +13,7 @@ pub type FloatMatrixSync = Arc<Matrix<RwLock<f64>>>;
 
 /// A very simple implementation of a matrix abstraction. It's
 /// simply a Vec an associated 'width' that is used for indexing.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Matrix<T> {
     data: Vec<T>,
     width: usize,
@@ -21,7 +25,7 @@ impl<T> Matrix<T> {
     }
 
     pub fn row(&self, i: usize) -> &[T] {
-        return &self.data[(i * self.width)..(i * self.width + self.width)];
+        &self.data[(i * self.width)..(i * self.width + self.width)]
     }
 
     /// Returns a Matrix with memory allocated for size*size elements.
@@ -53,14 +57,94 @@ impl<T> Index<(usize, usize)> for Matrix<T> {
 
     fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
         let i = y * self.width + x;
-        return &self.data[i];
+        &self.data[i]
     }
 }
 
 impl<T> IndexMut<(usize, usize)> for Matrix<T> {
     fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
         let i = y * self.width + x;
-        return &mut self.data[i];
+        &mut self.data[i]
+    }
+}
+
+/// A matrix where (i, j) and (j, i) are the same element, storing only the lower
+/// triangle (including the diagonal). Uses half the memory of a `Matrix`, and
+/// symmetric updates only need to write once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymmetricMatrix<T> {
+    data: Vec<T>,
+    size: usize,
+}
+
+impl<T> SymmetricMatrix<T> {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Position of element (i, j), with i >= j, in `data`.
+    #[inline]
+    fn position(i: usize, j: usize) -> usize {
+        i * (i + 1) / 2 + j
+    }
+
+    /// Elements (i, j) with j < i, which is a contiguous part of the row.
+    pub fn lower_row(&self, i: usize) -> &[T] {
+        let start = Self::position(i, 0);
+        &self.data[start..start + i]
+    }
+
+    pub fn lower_row_mut(&mut self, i: usize) -> &mut [T] {
+        let start = Self::position(i, 0);
+        &mut self.data[start..start + i]
+    }
+
+    /// Every `lower_row_mut` at once, so that rows can be updated in parallel.
+    pub fn lower_rows_mut(&mut self) -> Vec<&mut [T]> {
+        let mut rows = Vec::with_capacity(self.size);
+        let mut rest = &mut self.data[..];
+        for i in 0..self.size {
+            let (row, tail) = std::mem::take(&mut rest).split_at_mut(i + 1);
+            rows.push(&mut row[..i]);
+            rest = tail;
+        }
+        rows
+    }
+
+    /// Iterates over the whole row `i`, in order of column.
+    pub fn row(&self, i: usize) -> impl Iterator<Item = &T> + '_ {
+        let diagonal = Self::position(i, i);
+        let upper = (i + 1..self.size).scan(diagonal, move |position, j| {
+            // (j, i) is j elements after (j - 1, i)
+            *position += j;
+            Some(&self.data[*position])
+        });
+        self.data[diagonal - i..=diagonal].iter().chain(upper)
+    }
+}
+
+impl<T: Clone> SymmetricMatrix<T> {
+    /// Creates a size*size matrix with the given initial element occupying all
+    /// positions.
+    pub fn with_element(size: usize, element: T) -> SymmetricMatrix<T> {
+        SymmetricMatrix {
+            data: vec![element; size * (size + 1) / 2],
+            size,
+        }
+    }
+}
+
+impl<T> Index<(usize, usize)> for SymmetricMatrix<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        &self.data[Self::position(x.max(y), x.min(y))]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for SymmetricMatrix<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
+        &mut self.data[Self::position(x.max(y), x.min(y))]
     }
 }
 
@@ -73,69 +157,568 @@ impl<T> IndexMut<(usize, usize)> for Matrix<T> {
 /// creation code can be applied to both.
 ///
 /// The methods defined in this trait are inherently very specific to
-/// what is needed for  ACO algorithms.
+/// what is needed for  ACO algorithms. Both take a mask, indexed by city, with
+/// 1.0 for cities that can still be visited and 0.0 for visited ones, which
+/// lets rows be processed by the vectorised functions in `aco::kernel`.
 pub trait ColonyInfoMatrix {
-    /// Returns which members of the given row are not part of a given
-    /// set, and their respective values.
-    ///
-    /// This method is to be used by `choose_probabilistically`, where
-    /// the values will be used as weights for deciding which city to
-    /// visit next.
+    /// Chooses a city that wasn't visited with probability proportional to its
+    /// value in the given row, given `r` uniformly distributed in [0, 1).
     ///
-    /// It would be a nice generalization to make this return an iterator
-    /// (or even just the filtered row), but it creates some complications
-    /// with the borrow checker as `visited` is borrowed and needs to be accessed
-    /// within the iterator. With this signature, the method has to consume the iterator.
-    fn unvisited_weights(&self, row: usize, visited: &IndexSet<usize>) -> (Vec<usize>, Vec<f64>);
+    /// This method is to be used by `choose_probabilistically`. Returns `None`
+    /// if no unvisited city has a value.
+    fn roulette(&self, row: usize, unvisited: &[f64], r: f64) -> Option<usize>;
     /// Returns the index of the largest value of a given row in the
-    /// matrix, with a set of indices to be ignored.
+    /// matrix, ignoring visited cities. Returns `None` if no unvisited city has
+    /// a value.
     ///
     /// This is used in `choose_best_next` to find the highest-valued city.
-    fn filtered_row_max(&self, row: usize, excludes: &IndexSet<usize>) -> usize;
+    fn best_unvisited(&self, row: usize, unvisited: &[f64]) -> Option<usize>;
 }
 
 impl ColonyInfoMatrix for FloatMatrix {
-    fn unvisited_weights(&self, row: usize, visited: &IndexSet<usize>) -> (Vec<usize>, Vec<f64>) {
-        self.row(row)
-            .iter()
-            .enumerate()
-            .filter(|(i, _)| !visited.contains(i))
-            .unzip()
+    fn roulette(&self, row: usize, unvisited: &[f64], r: f64) -> Option<usize> {
+        kernel::roulette(self.row(row), unvisited, r)
     }
-    fn filtered_row_max(&self, row: usize, excludes: &IndexSet<usize>) -> usize {
-        let (i, _) = self
-            .row(row)
-            .iter()
-            .enumerate()
-            .filter(|(i, _)| !excludes.contains(i))
-            .max_by(|(_, a), (_, b)| a.partial_cmp(b).expect("failed comparison"))
-            .expect("failed max_by");
-        i
+    fn best_unvisited(&self, row: usize, unvisited: &[f64]) -> Option<usize> {
+        kernel::masked_max(self.row(row), unvisited).map(|(i, _)| i)
+    }
+}
+
+impl SymmetricMatrix<f64> {
+    /// Calls `f` with the index and value of every element of row `i` after the
+    /// diagonal that isn't masked. These are spread through the matrix, so
+    /// masked ones are skipped without reading them.
+    fn for_each_upper(&self, i: usize, unvisited: &[f64], mut f: impl FnMut(usize, f64)) {
+        let mut position = Self::position(i, i);
+        for (j, &mask) in unvisited.iter().enumerate().skip(i + 1) {
+            position += j;
+            if mask != 0.0 {
+                f(j, self.data[position] * mask);
+            }
+        }
+    }
+}
+
+impl ColonyInfoMatrix for SymmetricMatrix<f64> {
+    fn roulette(&self, row: usize, unvisited: &[f64], r: f64) -> Option<usize> {
+        let lower = self.lower_row(row);
+        let mut total = kernel::masked_sum(lower, unvisited);
+        self.for_each_upper(row, unvisited, |_, weight| total += weight);
+        if total <= 0.0 {
+            return None;
+        }
+        let mut remaining = match kernel::masked_select(lower, unvisited, r * total) {
+            Ok(j) => return Some(j),
+            Err(remaining) => remaining,
+        };
+        let mut chosen = None;
+        let mut last = None;
+        self.for_each_upper(row, unvisited, |j, weight| {
+            if weight > 0.0 {
+                last = Some(j);
+                if chosen.is_none() && remaining < weight {
+                    chosen = Some(j);
+                }
+                remaining -= weight;
+            }
+        });
+        chosen
+            .or(last)
+            .or_else(|| kernel::last_unmasked(lower, unvisited))
+    }
+    fn best_unvisited(&self, row: usize, unvisited: &[f64]) -> Option<usize> {
+        let mut best = kernel::masked_max(self.lower_row(row), unvisited);
+        self.for_each_upper(row, unvisited, |j, weight| {
+            if weight > best.map_or(0.0, |(_, max)| max) {
+                best = Some((j, weight));
+            }
+        });
+        best.map(|(j, _)| j)
     }
 }
 
 impl ColonyInfoMatrix for FloatMatrixSync {
-    fn unvisited_weights(&self, row: usize, visited: &IndexSet<usize>) -> (Vec<usize>, Vec<f64>) {
-        self.row(row)
+    /// Values are read once into a plain row, as other threads may change them
+    /// while the choice is made.
+    fn roulette(&self, row: usize, unvisited: &[f64], r: f64) -> Option<usize> {
+        let values: Vec<f64> = self.row(row).iter().map(|w| *w.read()).collect();
+        kernel::roulette(&values, unvisited, r)
+    }
+    fn best_unvisited(&self, row: usize, unvisited: &[f64]) -> Option<usize> {
+        let values: Vec<f64> = self.row(row).iter().map(|w| *w.read()).collect();
+        kernel::masked_max(&values, unvisited).map(|(i, _)| i)
+    }
+}
+
+/// Nearest neighbours of every city, used to restrict which edges keep their own
+/// values in a `CandidateMatrix`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CandidateLists {
+    /// The candidates of city `i` are at `lists[i * k..(i + 1) * k]`, closest first
+    lists: Vec<u32>,
+    k: usize,
+}
+
+impl CandidateLists {
+    pub fn new(lists: Vec<u32>, k: usize) -> CandidateLists {
+        CandidateLists { lists, k }
+    }
+
+    /// Number of candidates of each city.
+    pub fn len(&self) -> usize {
+        self.k
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.k == 0
+    }
+
+    /// Candidates of a city, closest first.
+    pub fn of(&self, city: usize) -> &[u32] {
+        &self.lists[city * self.k..(city + 1) * self.k]
+    }
+}
+
+/// Values for the edges between each city and its candidates, with a single
+/// shared value for every other edge. Needs memory for n*k values instead of
+/// n*n, so it is used for instances too large for a full `FloatMatrix`.
+///
+/// Edges are symmetric: setting (i, j) also sets (j, i). Setting an edge that
+/// is not a candidate edge of either city does nothing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CandidateMatrix {
+    candidates: Arc<CandidateLists>,
+    /// Parallel to the candidate lists
+    values: Vec<f64>,
+    default: f64,
+}
+
+impl CandidateMatrix {
+    pub fn new(candidates: Arc<CandidateLists>, value: f64) -> CandidateMatrix {
+        CandidateMatrix {
+            values: vec![value; candidates.lists.len()],
+            candidates,
+            default: value,
+        }
+    }
+
+    pub fn candidates(&self) -> &CandidateLists {
+        &self.candidates
+    }
+
+    /// Values of the candidate edges of a city, in the order of its candidates.
+    pub fn row(&self, i: usize) -> &[f64] {
+        &self.values[i * self.candidates.k..(i + 1) * self.candidates.k]
+    }
+
+    /// Value of every edge that is not a candidate edge.
+    pub fn default_value(&self) -> f64 {
+        self.default
+    }
+
+    /// Position of the value for (i, j) when `j` is a candidate of `i`.
+    fn slot(&self, i: usize, j: usize) -> Option<usize> {
+        self.candidates
+            .of(i)
             .iter()
-            .enumerate()
-            .filter(|(i, _)| !visited.contains(i))
-            .map(|(c, w)| (c, *w.read()))
-            .unzip()
-    }
-    fn filtered_row_max(&self, row: usize, excludes: &IndexSet<usize>) -> usize {
-        let (i, _) = self
-            .row(row)
+            .position(|&c| c as usize == j)
+            .map(|p| i * self.candidates.k + p)
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        match self.slot(i, j).or_else(|| self.slot(j, i)) {
+            Some(slot) => self.values[slot],
+            None => self.default,
+        }
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, value: f64) {
+        if let Some(slot) = self.slot(i, j) {
+            self.values[slot] = value;
+        }
+        if let Some(slot) = self.slot(j, i) {
+            self.values[slot] = value;
+        }
+    }
+}
+
+impl CandidateMatrix {
+    /// Candidates of city `i` with their values multiplied by the mask.
+    fn masked_row<'a>(
+        &'a self,
+        i: usize,
+        unvisited: &'a [f64],
+    ) -> impl Iterator<Item = (usize, f64)> + 'a {
+        self.candidates
+            .of(i)
             .iter()
-            .enumerate()
-            .filter(|(i, _)| !excludes.contains(i))
-            .max_by(|(_, a), (_, b)| {
-                (a.read())
-                    .partial_cmp(&b.read())
-                    .expect("failed comparison")
-            })
-            .expect("failed max_by");
-        i
+            .zip(self.row(i))
+            .map(move |(&c, &value)| (c as usize, value * unvisited[c as usize]))
+    }
+}
+
+/// Only candidates are considered, so no city is chosen once every candidate of
+/// the city was visited.
+impl ColonyInfoMatrix for CandidateMatrix {
+    fn roulette(&self, row: usize, unvisited: &[f64], r: f64) -> Option<usize> {
+        let total: f64 = self.masked_row(row, unvisited).map(|(_, w)| w).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut remaining = r * total;
+        let mut last = None;
+        for (c, weight) in self.masked_row(row, unvisited).filter(|&(_, w)| w > 0.0) {
+            if remaining < weight {
+                return Some(c);
+            }
+            remaining -= weight;
+            last = Some(c);
+        }
+        last
+    }
+    fn best_unvisited(&self, row: usize, unvisited: &[f64]) -> Option<usize> {
+        self.masked_row(row, unvisited)
+            .filter(|&(_, w)| w > 0.0)
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).expect("failed comparison"))
+            .map(|(c, _)| c)
+    }
+}
+
+/// Values kept for the edges of an instance, such as pheromones or heuristic
+/// information, either for every edge or only for the candidate edges of each
+/// city. Only full matrices keep a separate value for each direction of an
+/// edge, as asymmetric instances need; the rest update both directions at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EdgeValues {
+    /// Every edge in each direction, with the value from i to j in row i
+    Full(FloatMatrix),
+    /// Every edge, storing (i, j) and (j, i) once
+    Symmetric(SymmetricMatrix<f64>),
+    Candidates(CandidateMatrix),
+}
+
+impl EdgeValues {
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        match self {
+            EdgeValues::Full(matrix) => matrix[(j, i)],
+            EdgeValues::Symmetric(matrix) => matrix[(i, j)],
+            EdgeValues::Candidates(matrix) => matrix.get(i, j),
+        }
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, value: f64) {
+        match self {
+            EdgeValues::Full(matrix) => matrix[(j, i)] = value,
+            EdgeValues::Symmetric(matrix) => matrix[(i, j)] = value,
+            EdgeValues::Candidates(matrix) => matrix.set(i, j, value),
+        }
+    }
+
+    pub fn add(&mut self, i: usize, j: usize, amount: f64) {
+        let value = self.get(i, j) + amount;
+        self.set(i, j, value);
+    }
+
+    /// Replaces every value, except the diagonal of matrices, with the result
+    /// of `f` on it.
+    pub fn map(&mut self, f: impl Fn(f64) -> f64) {
+        match self {
+            EdgeValues::Full(matrix) => {
+                for i in 0..matrix.width() {
+                    for j in (0..matrix.width()).filter(|&j| j != i) {
+                        matrix[(j, i)] = f(matrix[(j, i)]);
+                    }
+                }
+            }
+            EdgeValues::Symmetric(matrix) => {
+                for i in 0..matrix.size() {
+                    matrix.lower_row_mut(i).iter_mut().for_each(|v| *v = f(*v));
+                }
+            }
+            EdgeValues::Candidates(matrix) => {
+                matrix.values.iter_mut().for_each(|v| *v = f(*v));
+                matrix.default = f(matrix.default);
+            }
+        }
+    }
+
+    /// Creates values for the same edges as `self`, calculating each edge's value
+    /// with `f`. The diagonal of matrices and the default value of candidate
+    /// matrices are set to 0.
+    pub fn with_same_edges(&self, f: impl Fn(usize, usize) -> f64) -> EdgeValues {
+        match self {
+            EdgeValues::Full(matrix) => {
+                let mut new = FloatMatrix::with_element(matrix.width(), 0.0);
+                for i in 0..matrix.width() {
+                    for j in (0..matrix.width()).filter(|&j| j != i) {
+                        new[(j, i)] = f(i, j);
+                    }
+                }
+                EdgeValues::Full(new)
+            }
+            EdgeValues::Symmetric(matrix) => {
+                let mut new = SymmetricMatrix::with_element(matrix.size(), 0.0);
+                for i in 0..matrix.size() {
+                    for (j, value) in new.lower_row_mut(i).iter_mut().enumerate() {
+                        *value = f(i, j);
+                    }
+                }
+                EdgeValues::Symmetric(new)
+            }
+            EdgeValues::Candidates(matrix) => {
+                let mut new = CandidateMatrix::new(Arc::clone(&matrix.candidates), 0.0);
+                let k = matrix.candidates.k;
+                for (slot, (value, &j)) in new
+                    .values
+                    .iter_mut()
+                    .zip(matrix.candidates.lists.iter())
+                    .enumerate()
+                {
+                    *value = f(slot / k, j as usize);
+                }
+                EdgeValues::Candidates(new)
+            }
+        }
+    }
+
+    /// Sets every value to the result of `f` on the values of the same edge in
+    /// `a` and `b`, which must store the same edges as `self`.
+    pub fn combine(&mut self, a: &EdgeValues, b: &EdgeValues, f: impl Fn(f64, f64) -> f64) {
+        use self::EdgeValues::*;
+        match (self, a, b) {
+            (Full(matrix), Full(a), Full(b)) => {
+                for i in 0..matrix.width() {
+                    for j in (0..matrix.width()).filter(|&j| j != i) {
+                        matrix[(j, i)] = f(a[(j, i)], b[(j, i)]);
+                    }
+                }
+            }
+            (Symmetric(matrix), Symmetric(a), Symmetric(b)) => {
+                for i in 0..matrix.size() {
+                    let rows = a.lower_row(i).iter().zip(b.lower_row(i));
+                    for (value, (&a, &b)) in matrix.lower_row_mut(i).iter_mut().zip(rows) {
+                        *value = f(a, b);
+                    }
+                }
+            }
+            (Candidates(matrix), Candidates(a), Candidates(b)) => {
+                assert_eq!(
+                    matrix.values.len(),
+                    a.values.len(),
+                    "different candidate lists"
+                );
+                assert_eq!(
+                    matrix.values.len(),
+                    b.values.len(),
+                    "different candidate lists"
+                );
+                for ((value, &a), &b) in matrix.values.iter_mut().zip(&a.values).zip(&b.values) {
+                    *value = f(a, b);
+                }
+                matrix.default = f(a.default, b.default);
+            }
+            _ => panic!("combining edge values stored in different ways"),
+        }
+    }
+
+    /// Row-parallel update fusing evaporation, deposits and bounding of the
+    /// values with recomputing the information combined from them. Every value
+    /// `v` becomes `bound(decay(v) + deposits)` and the same edge of `combined`
+    /// becomes `combine(v, o)`, with `o` the value of the edge in `other`.
+    ///
+    /// Gives exactly the same values as `map(decay)`, `deposits.apply`,
+    /// `map(bound)` and `combined.combine` one after the other.
+    pub fn par_update<D, B, C>(
+        &mut self,
+        decay: D,
+        deposits: &EdgeDeposits,
+        bound: B,
+        combined: &mut EdgeValues,
+        other: &EdgeValues,
+        combine: C,
+    ) where
+        D: Fn(f64) -> f64 + Sync,
+        B: Fn(f64) -> f64 + Sync,
+        C: Fn(f64, f64) -> f64 + Sync,
+    {
+        use self::EdgeValues::*;
+        let directed = matches!(self, Full(_));
+        let (starts, by_row) = deposits.by_row(self.size(), directed);
+        let row_deposits = |i: usize| by_row[starts[i]..starts[i + 1]].iter();
+        match (self, combined, other) {
+            (Full(values), Full(combined), Full(other)) => {
+                let width = values.width();
+                values
+                    .data
+                    .par_chunks_mut(width)
+                    .zip(combined.data.par_chunks_mut(width))
+                    .zip(other.data.par_chunks(width))
+                    .enumerate()
+                    .for_each(|(i, ((values, combined), other))| {
+                        let diagonal = (values[i], combined[i]);
+                        let deposits = row_deposits(i).map(|&(j, amount)| (j as usize, amount));
+                        update_row(values, combined, other, deposits, &decay, &bound, &combine);
+                        values[i] = diagonal.0;
+                        combined[i] = diagonal.1;
+                    });
+            }
+            (Symmetric(values), Symmetric(combined), Symmetric(other)) => {
+                values
+                    .lower_rows_mut()
+                    .into_par_iter()
+                    .zip(combined.lower_rows_mut())
+                    .enumerate()
+                    .for_each(|(i, (values, combined))| {
+                        // every edge is deposited on the rows of both of its cities,
+                        // the lower triangle only has it on the row of the larger one
+                        let deposits = row_deposits(i)
+                            .map(|&(j, amount)| (j as usize, amount))
+                            .filter(|&(j, _)| j < i);
+                        let other = other.lower_row(i);
+                        update_row(values, combined, other, deposits, &decay, &bound, &combine);
+                    });
+            }
+            (Candidates(values), Candidates(combined), Candidates(other)) => {
+                let candidates = Arc::clone(&values.candidates);
+                let k = candidates.k;
+                values
+                    .values
+                    .par_chunks_mut(k)
+                    .zip(combined.values.par_chunks_mut(k))
+                    .zip(other.values.par_chunks(k))
+                    .enumerate()
+                    .for_each(|(i, ((values, combined), other))| {
+                        let list = candidates.of(i);
+                        let deposits = row_deposits(i).filter_map(|&(j, amount)| {
+                            list.iter().position(|&c| c == j).map(|p| (p, amount))
+                        });
+                        update_row(values, combined, other, deposits, &decay, &bound, &combine);
+                    });
+                values.default = bound(decay(values.default));
+                combined.default = combine(values.default, other.default);
+            }
+            _ => panic!("updating edge values stored in different ways"),
+        }
+    }
+
+    /// Number of cities.
+    pub fn size(&self) -> usize {
+        match self {
+            EdgeValues::Full(matrix) => matrix.width(),
+            EdgeValues::Symmetric(matrix) => matrix.size(),
+            EdgeValues::Candidates(matrix) => matrix.candidates.lists.len() / matrix.candidates.k,
+        }
+    }
+
+    /// Candidate lists, if only candidate edges are stored.
+    pub fn candidates(&self) -> Option<&CandidateLists> {
+        match self {
+            EdgeValues::Candidates(matrix) => Some(matrix.candidates()),
+            _ => None,
+        }
+    }
+
+    /// Returns the full matrix, or `None` if the values are stored some other way.
+    pub fn into_full(self) -> Option<FloatMatrix> {
+        match self {
+            EdgeValues::Full(matrix) => Some(matrix),
+            _ => None,
+        }
+    }
+}
+
+/// Updates one row of values for `EdgeValues::par_update`. `deposits` are
+/// pairs of position in the row and amount.
+fn update_row(
+    values: &mut [f64],
+    combined: &mut [f64],
+    other: &[f64],
+    deposits: impl Iterator<Item = (usize, f64)>,
+    decay: &impl Fn(f64) -> f64,
+    bound: &impl Fn(f64) -> f64,
+    combine: &impl Fn(f64, f64) -> f64,
+) {
+    values.iter_mut().for_each(|v| *v = decay(*v));
+    for (j, amount) in deposits {
+        values[j] += amount;
+    }
+    for ((value, combined), &other) in values.iter_mut().zip(combined).zip(other) {
+        *value = bound(*value);
+        *combined = combine(*value, other);
+    }
+}
+
+/// Amounts to be added to edges, such as the pheromone deposited by ants, kept
+/// in the order they were added.
+#[derive(Debug, Clone, Default)]
+pub struct EdgeDeposits {
+    edges: Vec<(u32, u32, f64)>,
+}
+
+impl EdgeDeposits {
+    pub fn new() -> EdgeDeposits {
+        EdgeDeposits::default()
+    }
+
+    pub fn add(&mut self, i: usize, j: usize, amount: f64) {
+        self.edges.push((i as u32, j as u32, amount));
+    }
+
+    /// Removes every deposit, keeping the allocated memory.
+    pub fn clear(&mut self) {
+        self.edges.clear();
+    }
+
+    /// Adds every deposit to `values`, in order.
+    pub fn apply(&self, values: &mut EdgeValues) {
+        for &(i, j, amount) in &self.edges {
+            values.add(i as usize, j as usize, amount);
+        }
+    }
+
+    /// Deposits grouped by the row of each of the edge's cities, or only of the
+    /// city it starts from if `directed`, keeping their order. The deposits of
+    /// row `i` are at `starts[i]..starts[i + 1]`, as pairs of the other city and
+    /// amount.
+    fn by_row(&self, size: usize, directed: bool) -> (Vec<usize>, Vec<(u32, f64)>) {
+        let mut starts = vec![0; size + 1];
+        for &(i, j, _) in &self.edges {
+            starts[i as usize + 1] += 1;
+            if !directed {
+                starts[j as usize + 1] += 1;
+            }
+        }
+        for i in 0..size {
+            starts[i + 1] += starts[i];
+        }
+        let mut next = starts.clone();
+        let mut by_row = vec![(0, 0.0); starts[size]];
+        for &(i, j, amount) in &self.edges {
+            by_row[next[i as usize]] = (j, amount);
+            next[i as usize] += 1;
+            if !directed {
+                by_row[next[j as usize]] = (i, amount);
+                next[j as usize] += 1;
+            }
+        }
+        (starts, by_row)
+    }
+}
+
+impl ColonyInfoMatrix for EdgeValues {
+    fn roulette(&self, row: usize, unvisited: &[f64], r: f64) -> Option<usize> {
+        match self {
+            EdgeValues::Full(matrix) => matrix.roulette(row, unvisited, r),
+            EdgeValues::Symmetric(matrix) => matrix.roulette(row, unvisited, r),
+            EdgeValues::Candidates(matrix) => matrix.roulette(row, unvisited, r),
+        }
+    }
+    fn best_unvisited(&self, row: usize, unvisited: &[f64]) -> Option<usize> {
+        match self {
+            EdgeValues::Full(matrix) => matrix.best_unvisited(row, unvisited),
+            EdgeValues::Symmetric(matrix) => matrix.best_unvisited(row, unvisited),
+            EdgeValues::Candidates(matrix) => matrix.best_unvisited(row, unvisited),
+        }
     }
 }
 
@@ -146,7 +729,16 @@ impl ColonyInfoMatrix for FloatMatrixSync {
 pub fn generate_pheromone_matrix(size: usize, value: f64) -> FloatMatrix {
     let mut matrix = Matrix::with_element(size, value);
     for i in 0..size {
-        matrix[(i, i)] = std::f64::MAX;
+        matrix[(i, i)] = f64::MAX;
+    }
+    matrix
+}
+
+/// Same as `generate_pheromone_matrix`, for symmetric instances.
+pub fn generate_symmetric_pheromone_matrix(size: usize, value: f64) -> SymmetricMatrix<f64> {
+    let mut matrix = SymmetricMatrix::with_element(size, value);
+    for i in 0..size {
+        matrix[(i, i)] = f64::MAX;
     }
     matrix
 }
@@ -155,7 +747,7 @@ pub fn generate_pheromone_matrix(size: usize, value: f64) -> FloatMatrix {
 /// matrix in an `Arc`. Used with the parallelized version of ACS.
 pub fn convert_to_sync(matrix: FloatMatrix) -> FloatMatrixSync {
     let width = matrix.width;
-    let sync_vec = matrix.data.into_iter().map(|x| RwLock::new(x)).collect();
+    let sync_vec = matrix.data.into_iter().map(RwLock::new).collect();
 
     Arc::new(Matrix {
         data: sync_vec,
@@ -163,45 +755,170 @@ pub fn convert_to_sync(matrix: FloatMatrix) -> FloatMatrixSync {
     })
 }
 
-/// Calculates the value of a single tour (assumes first node is 0).
-/// Was used while testing, left in just in case it is ever relevant again.
-#[allow(dead_code)]
-pub fn value_of_tour(distances: &IntegerMatrix, tour: &[usize]) -> u32 {
+/// Copies the current values of a `FloatMatrixSync` into a plain matrix.
+pub fn convert_from_sync(matrix: &FloatMatrixSync) -> FloatMatrix {
+    Matrix {
+        data: matrix.data.iter().map(|x| *x.read()).collect(),
+        width: matrix.width,
+    }
+}
+
+/// Calculates the length of a tour, including the edge back to the first city.
+pub fn value_of_tour(data: &InstanceData, tour: &[usize]) -> u32 {
     use itertools::Itertools;
     let mut length = 0;
     for (&i, &j) in tour.iter().tuple_windows() {
-        length += distances[(i, j)];
+        length += data.distance(i, j);
     }
-    length += distances[(tour[tour.len() - 1], tour[0])];
+    length += data.distance(tour[tour.len() - 1], tour[0]);
     length
 }
 
+/// Why a sequence of cities is not a tour of an instance. Cities are numbered
+/// from 1 when displayed, as in TSPLIB files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TourError {
+    /// The tour does not have as many cities as the instance
+    WrongLength { expected: usize, found: usize },
+    /// A city that is not part of the instance
+    UnknownCity(usize),
+    /// A city visited more than once
+    RepeatedCity(usize),
+    /// The length recorded for the tour is not its actual length
+    LengthMismatch { recorded: u32, actual: u32 },
+}
+
+impl fmt::Display for TourError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TourError::WrongLength { expected, found } => {
+                write!(f, "tour has {} cities, expected {}", found, expected)
+            }
+            TourError::UnknownCity(city) => write!(f, "city {} is not in the instance", city + 1),
+            TourError::RepeatedCity(city) => write!(f, "city {} is visited twice", city + 1),
+            TourError::LengthMismatch { recorded, actual } => write!(
+                f,
+                "tour has length {}, but {} was recorded",
+                actual, recorded
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TourError {}
+
+/// Checks that a tour visits every one of `size` cities exactly once.
+pub fn check_tour(tour: &[usize], size: usize) -> Result<(), TourError> {
+    if tour.len() != size {
+        return Err(TourError::WrongLength {
+            expected: size,
+            found: tour.len(),
+        });
+    }
+    let mut visited = vec![false; size];
+    for &city in tour {
+        match visited.get_mut(city) {
+            None => return Err(TourError::UnknownCity(city)),
+            Some(true) => return Err(TourError::RepeatedCity(city)),
+            Some(seen) => *seen = true,
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a tour visits every city of the instance exactly once and
+/// calculates its length, which must match `recorded` if given. Returns the
+/// length.
+pub fn verify_tour(
+    data: &InstanceData,
+    tour: &[usize],
+    recorded: Option<u32>,
+) -> Result<u32, TourError> {
+    check_tour(tour, data.size)?;
+    let actual = value_of_tour(data, tour);
+    match recorded {
+        Some(recorded) if recorded != actual => Err(TourError::LengthMismatch { recorded, actual }),
+        _ => Ok(actual),
+    }
+}
+
 pub mod distance_funcs {
-    pub fn euc_2d(i: (i32, i32), j: (i32, i32)) -> u32 {
-        (((i.0 - j.0).pow(2) + (i.1 - j.1).pow(2)) as f64)
+    pub fn euc_2d(i: (f64, f64), j: (f64, f64)) -> u32 {
+        ((i.0 - j.0).powi(2) + (i.1 - j.1).powi(2)).sqrt().round() as u32
+    }
+
+    pub fn euc_3d(i: (f64, f64, f64), j: (f64, f64, f64)) -> u32 {
+        ((i.0 - j.0).powi(2) + (i.1 - j.1).powi(2) + (i.2 - j.2).powi(2))
             .sqrt()
             .round() as u32
     }
+
+    /// Mean radius of the Earth in kilometres
+    const EARTH_RADIUS: f64 = 6_371.0;
+
+    /// Great-circle distance in kilometres, rounded, between two (latitude,
+    /// longitude) points in decimal degrees. Kilometres, as in TSPLIB GEO
+    /// instances, keep the length of tours around the Earth within a `u32`,
+    /// which metres would overflow after a couple of hundred cities.
+    pub fn haversine(i: (f64, f64), j: (f64, f64)) -> u32 {
+        let (lat_i, lat_j) = (i.0.to_radians(), j.0.to_radians());
+        let half_lat = (lat_j - lat_i) / 2.0;
+        let half_lon = (j.1 - i.1).to_radians() / 2.0;
+        let a = half_lat.sin().powi(2) + lat_i.cos() * lat_j.cos() * half_lon.sin().powi(2);
+        (2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()).round() as u32
+    }
 }
 
 #[cfg(test)]
 pub mod test {
     use super::distance_funcs::*;
-    use super::Matrix;
+    use super::{
+        verify_tour, CandidateLists, CandidateMatrix, ColonyInfoMatrix, EdgeDeposits, EdgeValues,
+        Matrix, SymmetricMatrix, TourError,
+    };
+    use crate::instance_data::InstanceData;
+    use std::sync::Arc;
 
     #[test]
     pub fn euc_2d_1010_2020() {
-        let x = (10, 10);
-        let y = (20, 20);
+        let x = (10.0, 10.0);
+        let y = (20.0, 20.0);
         assert_eq!(euc_2d(x, y), 14);
     }
 
+    #[test]
+    pub fn haversine_of_one_degree() {
+        // a degree of latitude, or of longitude on the equator
+        assert_eq!(haversine((10.0, 20.0), (11.0, 20.0)), 111);
+        assert_eq!(haversine((0.0, -0.5), (0.0, 0.5)), 111);
+        // half way around the Earth
+        assert_eq!(haversine((0.0, 0.0), (0.0, 180.0)), 20_015);
+    }
+
+    #[test]
+    pub fn verify_tour_recomputes_length() {
+        let data = InstanceData::from_coordinates(&[(0.0, 0.0), (0.0, 3.0), (4.0, 3.0)]);
+        assert_eq!(verify_tour(&data, &[0, 1, 2], None), Ok(12));
+        assert_eq!(verify_tour(&data, &[2, 0, 1], Some(12)), Ok(12));
+        assert_eq!(
+            verify_tour(&data, &[0, 1, 2], Some(11)),
+            Err(TourError::LengthMismatch {
+                recorded: 11,
+                actual: 12
+            })
+        );
+        assert_eq!(
+            verify_tour(&data, &[0, 1, 3], None),
+            Err(TourError::UnknownCity(3))
+        );
+    }
+
     #[test]
     pub fn matrix_test() {
         let size = 10;
         let mut matrix = Matrix::with_element(size, 0);
         for i in 0..size * size {
-            matrix.data[i] = 0 + i;
+            matrix.data[i] = i;
         }
         assert_eq!(matrix.data[10], matrix[(0, 1)]);
         assert_eq!(matrix.data[1], matrix[(1, 0)]);
@@ -210,4 +927,121 @@ pub mod test {
         assert_eq!(matrix.row(3), &[30, 31, 32, 33, 34, 35, 36, 37, 38, 39]);
     }
 
+    #[test]
+    pub fn symmetric_matrix_test() {
+        let size = 5;
+        let mut matrix = SymmetricMatrix::with_element(size, 0);
+        for i in 0..size {
+            for j in 0..=i {
+                matrix[(i, j)] = 10 * i + j;
+            }
+        }
+        assert_eq!(matrix[(1, 3)], 31);
+        assert_eq!(matrix[(3, 1)], 31);
+        assert_eq!(matrix.lower_row(3), &[30, 31, 32]);
+        for i in 0..size {
+            let row: Vec<usize> = matrix.row(i).cloned().collect();
+            let expected: Vec<usize> = (0..size).map(|j| matrix[(i, j)]).collect();
+            assert_eq!(row, expected);
+        }
+    }
+
+    #[test]
+    pub fn candidate_matrix_test() {
+        // 0 and 1 are candidates of each other, 2 only has 1 as a candidate
+        let lists = CandidateLists::new(vec![1, 0, 1], 1);
+        let mut matrix = CandidateMatrix::new(Arc::new(lists), 0.5);
+        matrix.set(2, 1, 3.0);
+        assert_eq!(matrix.get(1, 2), 3.0);
+        assert_eq!(matrix.get(0, 1), 0.5);
+        matrix.set(0, 2, 7.0);
+        assert_eq!(matrix.get(0, 2), 0.5);
+        assert_eq!(matrix.best_unvisited(2, &[1.0, 1.0, 0.0]), Some(1));
+        assert_eq!(matrix.best_unvisited(2, &[1.0, 0.0, 0.0]), None);
+        assert_eq!(matrix.roulette(1, &[1.0; 3], 0.99), Some(0));
+    }
+
+    #[test]
+    pub fn symmetric_matrix_chooses_like_full_matrix() {
+        let size = 21;
+        let mut symmetric = SymmetricMatrix::with_element(size, 0.0);
+        let mut full = Matrix::with_element(size, 0.0);
+        for i in 0..size {
+            for j in 0..i {
+                let value = ((i * 7 + j * 3) % 11) as f64 + 1.0;
+                symmetric[(i, j)] = value;
+                full[(i, j)] = value;
+                full[(j, i)] = value;
+            }
+        }
+        let unvisited: Vec<f64> = (0..size).map(|c| (c % 3 != 0) as u8 as f64).collect();
+        for row in [0, 4, 10, 20].iter().cloned() {
+            assert_eq!(
+                symmetric.best_unvisited(row, &unvisited),
+                full.best_unvisited(row, &unvisited)
+            );
+            for &r in &[0.0, 0.3, 0.5, 0.9, 0.999] {
+                assert_eq!(
+                    symmetric.roulette(row, &unvisited, r),
+                    full.roulette(row, &unvisited, r),
+                    "row {} r {}",
+                    row,
+                    r
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn full_edge_values_keep_each_direction() {
+        let mut values = EdgeValues::Full(Matrix::with_element(3, 0.0))
+            .with_same_edges(|i, j| (10 * i + j) as f64);
+        assert_eq!(values.get(1, 2), 12.0);
+        assert_eq!(values.get(2, 1), 21.0);
+        values.add(0, 2, 5.0);
+        assert_eq!(values.get(0, 2), 7.0);
+        assert_eq!(values.get(2, 0), 20.0);
+        // ants on city 2 choose by the values of the edges leaving it
+        assert_eq!(values.best_unvisited(2, &[1.0, 1.0, 0.0]), Some(1));
+        assert_eq!(values.best_unvisited(0, &[0.0, 1.0, 1.0]), Some(2));
+    }
+
+    #[test]
+    pub fn par_update_matches_sequential_steps() {
+        let size = 6;
+        let lists: Vec<u32> = (0..size)
+            .flat_map(|i| vec![(i + 1) % size, (i + 3) % size])
+            .map(|c| c as u32)
+            .collect();
+        let candidates = Arc::new(CandidateLists::new(lists, 2));
+        let mut deposits = EdgeDeposits::new();
+        for &(i, j, amount) in [(0, 1, 0.5), (3, 0, 0.25), (1, 0, 0.125), (4, 2, 2.0)].iter() {
+            deposits.add(i, j, amount);
+        }
+        let value = |i: usize, j: usize| ((i + j) % 4) as f64 + 1.0;
+        for layout in [
+            EdgeValues::Full(Matrix::with_element(size, 0.0)),
+            EdgeValues::Symmetric(SymmetricMatrix::with_element(size, 0.0)),
+            EdgeValues::Candidates(CandidateMatrix::new(candidates, 0.0)),
+        ] {
+            let mut values = layout.with_same_edges(value);
+            let other = layout.with_same_edges(|i, j| value(j, i) * 0.5);
+            let mut combined = layout.with_same_edges(|_, _| 0.0);
+            let (mut expected_values, mut expected_combined) = (values.clone(), combined.clone());
+            let decay = |v: f64| v * 0.75;
+            let bound = |v: f64| v.clamp(1.0, 3.0);
+            let combine = |a: f64, b: f64| a * b;
+            expected_values.map(decay);
+            deposits.apply(&mut expected_values);
+            expected_values.map(bound);
+            expected_combined.combine(&expected_values, &other, combine);
+            values.par_update(decay, &deposits, bound, &mut combined, &other, combine);
+            for i in 0..size {
+                for j in (0..size).filter(|&j| j != i) {
+                    assert_eq!(values.get(i, j), expected_values.get(i, j), "{:?}", (i, j));
+                    assert_eq!(combined.get(i, j), expected_combined.get(i, j));
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,134 @@
+use crate::{format_arg, instance_format};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use parallelized_acotsp::instance_data::InstanceData;
+use parallelized_acotsp::instance_reader::read_instance_file_as;
+use parallelized_acotsp::result_file::parse_result;
+use parallelized_acotsp::tsplibreader::{read_tour, TourFile};
+use parallelized_acotsp::util::{verify_tour, TourError};
+use std::fs::read_to_string;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("validate")
+        .about("Checks that a tour visits every city of an instance once and reports its length")
+        .arg(
+            Arg::with_name("INSTANCE")
+                .help("File of the instance")
+                .required(true),
+        )
+        .arg(format_arg())
+        .arg(
+            Arg::with_name("TOUR")
+                .help("TSPLIB .tour file, or result file written by a run")
+                .required(true),
+        )
+}
+
+/// Reads the contents of either a TSPLIB `.tour` file or a result file. Result
+/// files also give the length recorded for the tour.
+pub fn read_tour_or_result(contents: &str) -> (TourFile, Option<u32>) {
+    if contents.contains("TOUR_SECTION") {
+        (read_tour(contents.as_bytes()), None)
+    } else {
+        let result = parse_result(contents).unwrap_or_else(|e| panic!("{}", e));
+        let tour_file = TourFile {
+            name: String::new(),
+            dimension: None,
+            tour: result.best_tour,
+        };
+        (tour_file, Some(result.best_length))
+    }
+}
+
+/// Checks the tour in a `.tour` or result file, returning its length.
+fn validate(data: &InstanceData, contents: &str) -> Result<u32, TourError> {
+    let (tour_file, recorded_length) = read_tour_or_result(contents);
+    tour_file.check(data)?;
+    verify_tour(data, &tour_file.tour, recorded_length)
+}
+
+/// Runs the subcommand. Returns whether the tour is valid.
+pub fn run(matches: &ArgMatches) -> bool {
+    let instance_file = matches
+        .value_of("INSTANCE")
+        .expect("failed parsing argument");
+    let tour_file = matches.value_of("TOUR").expect("failed parsing argument");
+    let instance = read_instance_file_as(instance_file, instance_format(matches));
+    let contents = read_to_string(tour_file).expect("Failed opening tour file");
+    match validate(&instance.data, &contents) {
+        Ok(length) => {
+            println!(
+                "{}: valid tour of {}, length {}",
+                tour_file, instance.metadata.name, length
+            );
+            true
+        }
+        Err(e) => {
+            println!(
+                "{}: invalid tour of {}: {}",
+                tour_file, instance.metadata.name, e
+            );
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A right triangle with sides 3, 4 and 5.
+    fn triangle() -> InstanceData {
+        InstanceData::from_coordinates(&[(0.0, 0.0), (0.0, 3.0), (4.0, 3.0)])
+    }
+
+    fn tour_file(cities: &str) -> String {
+        format!(
+            "NAME: t.tour\nTYPE: TOUR\nDIMENSION: 3\nTOUR_SECTION\n{}\n-1\nEOF\n",
+            cities
+        )
+    }
+
+    fn result_file(length: u32, tour: &str) -> String {
+        format!(
+            "BEST FOUND: {}\nSEED: 1\nBEST TOUR: [{}]\nFound on iteration 1 at 0.005s\n\
+             ==========================\n-----Iter 1, new_best: true\nlength: {} time 0.005s\n",
+            length, tour, length
+        )
+    }
+
+    #[test]
+    fn tours_and_result_files_are_checked() {
+        let data = triangle();
+        assert_eq!(validate(&data, &tour_file("1\n3\n2")), Ok(12));
+        assert_eq!(validate(&data, &result_file(12, "1, 2, 3")), Ok(12));
+
+        assert_eq!(
+            validate(&data, &tour_file("1\n2")),
+            Err(TourError::WrongLength {
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            validate(&data, &tour_file("1\n2\n2")),
+            Err(TourError::RepeatedCity(1))
+        );
+        assert_eq!(
+            validate(&data, &result_file(12, "1, 2, 4")),
+            Err(TourError::UnknownCity(3))
+        );
+        assert_eq!(
+            validate(&data, &result_file(10, "1, 2, 3")),
+            Err(TourError::LengthMismatch {
+                recorded: 10,
+                actual: 12
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn unreadable_result_file_is_rejected() {
+        read_tour_or_result("BEST FOUND: twelve\n");
+    }
+}